@@ -0,0 +1,55 @@
+//! 每个 openid 最近收到的 webhook 与管理接口对存档的改动, 用于排查 "存档没有更新" 一类的问题.
+//!
+//! [`AUDIT_TABLE`] 的键为 openid, 值为按时间从旧到新排列的 [`AuditEntry`] 列表,
+//! 最多保留 [`MAX_ENTRIES`] 条. 只记录事件类型, openid, 存档 id 与处理结果, 不保存请求体.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::types::{KVStorage, KVTable};
+
+pub const AUDIT_TABLE: &str = "webhook_audit";
+
+/// 每个 openid 保留的记录数
+pub const MAX_ENTRIES: usize = 20;
+
+/// 一次 webhook 或管理操作的处理记录
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    /// 收到请求的时间, Unix 毫秒
+    pub received_at: u64,
+    pub r#type: String,
+    pub action: String,
+    /// 旧版本写入的记录没有该字段, 读取时为空
+    #[serde(default)]
+    pub openid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_object_id: Option<String>,
+    /// `ok`, `unhandled` 或错误码
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// `openid` 的全部记录, 由旧到新
+pub async fn entries<KV: KVStorage>(kv: &KV, openid: &str) -> Result<Vec<AuditEntry>, Error> {
+    match kv.open_table(AUDIT_TABLE).await?.get(openid).await? {
+        Some(raw) => serde_json::from_slice(&raw).map_err(|e| Error::Storage(e.to_string())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 追加一条记录, 超出 [`MAX_ENTRIES`] 时丢弃最旧的
+pub async fn append<KV: KVStorage>(kv: &KV, openid: &str, entry: AuditEntry) -> Result<(), Error> {
+    let mut entries = entries(kv, openid).await?;
+    entries.push(entry);
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.drain(..excess);
+    let raw = serde_json::to_vec(&entries).map_err(|e| Error::Encode(e.to_string()))?;
+    kv.open_table(AUDIT_TABLE).await?.put(openid, &raw).await
+}
+
+pub async fn remove<KV: KVStorage>(kv: &KV, openid: &str) -> Result<(), Error> {
+    kv.open_table(AUDIT_TABLE).await?.delete(openid).await
+}
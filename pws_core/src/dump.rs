@@ -0,0 +1,306 @@
+//! 与存储后端无关的导出与导入, 用于备份和在不同 [`KVStorage`] 之间迁移.
+//!
+//! 格式为 NDJSON, 每行一个对象, 以 `kind` 区分:
+//!
+//! ```text
+//! {"kind":"header","format":"pws-dump","version":1}
+//! {"kind":"blob","hash":"<blake2s>","value":"<base64>"}
+//! {"kind":"record","table":"user","key":"<openid>","value":"<base64>"}
+//! {"kind":"end","records":1,"blobs":1}
+//! ```
+//!
+//! 带有元数据或过期时间的记录额外包含 `metadata` 与 `expires_at` 字段, 见 [`PutOptions`].
+//!
+//! blob 写在记录之前, 导入中断时不会留下引用不存在 blob 的元数据;
+//! 缺少 `end` 行的文件视为不完整. 读写都是逐行进行的, 表中的键也分页列出, 不会把整个数据集载入内存.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::i18n::{Lang, Msg};
+use crate::store::blob_hash;
+use crate::types::{BlobStore, Entry, KVStorage, KVTable, PutOptions, TABLES};
+
+pub const FORMAT: &str = "pws-dump";
+pub const VERSION: u32 = 1;
+
+/// 导出与复制时每次从表中列出的键数, 见 [`KVTable::list_page`]
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Line {
+    Header {
+        format: String,
+        version: u32,
+    },
+    Blob {
+        hash: String,
+        value: String,
+    },
+    Record {
+        table: String,
+        key: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<Map<String, Value>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>,
+    },
+    End {
+        records: u64,
+        blobs: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    Io(String),
+    InvalidLine { line: u64, message: String },
+    Unsupported(String),
+    Truncated,
+    HashMismatch(String),
+    Storage(crate::error::Error),
+}
+
+impl Error {
+    pub fn msg(&self) -> Msg<'_> {
+        match self {
+            Error::Io(e) => Msg::DumpIoError(e),
+            Error::InvalidLine { line, message } => Msg::DumpInvalidLine {
+                line: *line,
+                error: message,
+            },
+            Error::Unsupported(header) => Msg::DumpUnsupported(header),
+            Error::Truncated => Msg::DumpTruncated,
+            Error::HashMismatch(hash) => Msg::DumpHashMismatch(hash),
+            Error::Storage(e) => e.msg(),
+        }
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        self.msg().text(lang)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Lang::En))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<crate::error::Error> for Error {
+    fn from(e: crate::error::Error) -> Self {
+        Error::Storage(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+/// 导出或导入的条目数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub records: u64,
+    pub blobs: u64,
+}
+
+fn write_line<W: Write>(out: &mut W, line: &Line) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, line).map_err(|e| Error::Io(e.to_string()))?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+/// 将全部 blob 与 [`TABLES`] 中的每张表写入 `out`
+pub async fn export<KV: KVStorage, W: Write>(kv: &KV, mut out: W) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    write_line(
+        &mut out,
+        &Line::Header {
+            format: FORMAT.to_owned(),
+            version: VERSION,
+        },
+    )?;
+
+    for blob in kv.blobs().list().await? {
+        // 列出之后才被回收的 blob 直接跳过
+        let Some(data) = kv.blobs().get(&blob.hash).await? else {
+            continue;
+        };
+        write_line(
+            &mut out,
+            &Line::Blob {
+                hash: blob.hash,
+                value: STANDARD.encode(data),
+            },
+        )?;
+        stats.blobs += 1;
+    }
+
+    for &name in TABLES {
+        let table = kv.open_table(name).await?;
+        let mut cursor = None;
+        loop {
+            let page = table.list_page("", cursor.as_deref(), PAGE_SIZE).await?;
+            for key in page.keys {
+                let Some(Entry {
+                    value,
+                    metadata,
+                    expires_at,
+                }) = table.get_with_metadata(&key).await?
+                else {
+                    continue;
+                };
+                write_line(
+                    &mut out,
+                    &Line::Record {
+                        table: name.to_owned(),
+                        key,
+                        value: STANDARD.encode(value),
+                        metadata,
+                        expires_at,
+                    },
+                )?;
+                stats.records += 1;
+            }
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+    }
+
+    write_line(
+        &mut out,
+        &Line::End {
+            records: stats.records,
+            blobs: stats.blobs,
+        },
+    )?;
+    out.flush()?;
+    Ok(stats)
+}
+
+/// 读取 [`export`] 的输出并写入 `kv`, 已存在的键会被覆盖.
+/// 出错时已导入的部分不会回滚.
+pub async fn import<KV: KVStorage, R: BufRead>(kv: &KV, input: R) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut tables: HashMap<String, KV::Table> = HashMap::new();
+    let mut header = false;
+
+    for (index, line) in input.lines().enumerate() {
+        let line_no = index as u64 + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |message: String| Error::InvalidLine {
+            line: line_no,
+            message,
+        };
+        let decode = |value: &str| STANDARD.decode(value).map_err(|e| invalid(e.to_string()));
+
+        let parsed: Line = match serde_json::from_str(&line) {
+            Ok(parsed) => parsed,
+            Err(_) if !header => return Err(Error::Unsupported(line.chars().take(80).collect())),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+
+        match parsed {
+            Line::Header { format, version } if !header => {
+                if format != FORMAT || version != VERSION {
+                    return Err(Error::Unsupported(format!("{} v{}", format, version)));
+                }
+                header = true;
+            }
+            _ if !header => return Err(Error::Unsupported(line.chars().take(80).collect())),
+            Line::Header { .. } => return Err(invalid("duplicate header".to_owned())),
+            Line::Blob { hash, value } => {
+                let data = decode(&value)?;
+                if blob_hash(&data) != hash {
+                    return Err(Error::HashMismatch(hash));
+                }
+                kv.blobs().put(&hash, &data).await?;
+                stats.blobs += 1;
+            }
+            Line::Record {
+                table,
+                key,
+                value,
+                metadata,
+                expires_at,
+            } => {
+                let value = decode(&value)?;
+                let options = PutOptions {
+                    metadata,
+                    expires_at,
+                };
+                if !tables.contains_key(&table) {
+                    let opened = kv.open_table(&table).await?;
+                    tables.insert(table.clone(), opened);
+                }
+                tables[&table]
+                    .put_with_options(&key, &value, &options)
+                    .await?;
+                stats.records += 1;
+            }
+            Line::End { records, blobs } => {
+                if (Stats { records, blobs }) != stats {
+                    return Err(invalid(format!(
+                        "expected {} records and {} blobs, read {} and {}",
+                        records, blobs, stats.records, stats.blobs
+                    )));
+                }
+                return Ok(stats);
+            }
+        }
+    }
+    Err(Error::Truncated)
+}
+
+/// 不经过序列化, 直接把 `from` 的全部 blob 与表复制到 `to`
+pub async fn copy<A: KVStorage, B: KVStorage>(from: &A, to: &B) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    for blob in from.blobs().list().await? {
+        if let Some(data) = from.blobs().get(&blob.hash).await? {
+            to.blobs().put(&blob.hash, &data).await?;
+            stats.blobs += 1;
+        }
+    }
+    for &name in TABLES {
+        let source = from.open_table(name).await?;
+        let target = to.open_table(name).await?;
+        let mut cursor = None;
+        loop {
+            let page = source.list_page("", cursor.as_deref(), PAGE_SIZE).await?;
+            for key in page.keys {
+                if let Some(entry) = source.get_with_metadata(&key).await? {
+                    let options = PutOptions {
+                        metadata: entry.metadata,
+                        expires_at: entry.expires_at,
+                    };
+                    target
+                        .put_with_options(&key, &entry.value, &options)
+                        .await?;
+                    stats.records += 1;
+                }
+            }
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+    }
+    Ok(stats)
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use axum::Json;
+use axum::http::header::CONTENT_LANGUAGE;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::i18n::{Lang, Msg};
+use crate::save;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    Save(save::Error),
+    Storage(String),
+    Fetch(String),
+    Encode(String),
+    InvalidPayload(String),
+    MissingSign,
+    InvalidSign,
+    Unauthorized,
+    NotFound,
+    NotAcceptable,
+    Http(StatusCode),
+}
+
+#[derive(Serialize)]
+pub struct ErrorBody<'a> {
+    pub code: &'a str,
+    pub message: String,
+    pub request_id: &'a str,
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Save(e) => e.code(),
+            Error::Storage(_) => "storage_error",
+            Error::Fetch(_) => "fetch_error",
+            Error::Encode(_) => "encode_error",
+            Error::InvalidPayload(_) => "invalid_payload",
+            Error::MissingSign => "missing_sign",
+            Error::InvalidSign => "invalid_sign",
+            Error::Unauthorized => "unauthorized",
+            Error::NotFound => "not_found",
+            Error::NotAcceptable => "not_acceptable",
+            Error::Http(status) => match *status {
+                StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+                StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+                StatusCode::REQUEST_TIMEOUT => "request_timeout",
+                s if s.is_client_error() => "bad_request",
+                _ => "internal_error",
+            },
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Save(_) | Error::Storage(_) | Error::Encode(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Error::Fetch(_) => StatusCode::BAD_GATEWAY,
+            Error::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            Error::MissingSign | Error::InvalidSign | Error::Unauthorized => {
+                StatusCode::UNAUTHORIZED
+            }
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            Error::Http(status) => *status,
+        }
+    }
+
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Error::Save(e) => e.field(),
+            _ => None,
+        }
+    }
+
+    pub fn msg(&self) -> Msg<'_> {
+        match self {
+            Error::Save(e) => e.msg(),
+            Error::Storage(e) => Msg::StorageError(e),
+            Error::Fetch(e) => Msg::FetchError(e),
+            Error::Encode(e) => Msg::EncodeError(e),
+            Error::InvalidPayload(e) => Msg::InvalidPayload(e),
+            Error::MissingSign => Msg::MissingSign,
+            Error::InvalidSign => Msg::InvalidSign,
+            Error::Unauthorized => Msg::Unauthorized,
+            Error::NotFound => Msg::NotFound,
+            Error::NotAcceptable => Msg::NotAcceptable,
+            Error::Http(status) => Msg::HttpError(status.canonical_reason().unwrap_or("Unknown")),
+        }
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        self.msg().text(lang)
+    }
+
+    /// 渲染带请求 ID 的 JSON 错误体
+    pub fn to_response(&self, request_id: &str, lang: Lang) -> Response {
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.message(lang),
+            request_id,
+        };
+        let mut resp = (self.status(), Json(body)).into_response();
+        resp.headers_mut()
+            .insert(CONTENT_LANGUAGE, HeaderValue::from_static(lang.tag()));
+        resp
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Lang::En))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<save::Error> for Error {
+    fn from(e: save::Error) -> Self {
+        Error::Save(e)
+    }
+}
+
+/// 错误体由 `middleware::request_id` 统一渲染, 这里只携带状态码与错误本身
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let mut resp = self.status().into_response();
+        resp.extensions_mut().insert(self);
+        resp
+    }
+}
@@ -0,0 +1,549 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::utils::quality_values;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    #[serde(rename = "zh-CN", alias = "zh", alias = "zh_CN")]
+    ZhCN,
+    #[serde(rename = "en", alias = "en-US", alias = "en_US")]
+    En,
+}
+
+impl Lang {
+    pub fn tag(self) -> &'static str {
+        match self {
+            Lang::ZhCN => "zh-CN",
+            Lang::En => "en",
+        }
+    }
+
+    /// 匹配语言标签的主语言部分, 如 `zh-Hans-CN` 与 `en-GB`
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Lang::ZhCN),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// 按 q 值从 `Accept-Language` 中选出受支持的语言
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut best: Option<(Lang, f32)> = None;
+
+        for (tag, q) in quality_values(header) {
+            if q == 0.0 {
+                continue;
+            }
+
+            if let Some(lang) = Self::parse(tag)
+                && best.is_none_or(|(_, best_q)| q > best_q)
+            {
+                best = Some((lang, q));
+            }
+        }
+
+        best.map(|(lang, _)| lang)
+    }
+}
+
+/// 所有面向用户的文本, 包括错误消息与日志
+pub enum Msg<'a> {
+    ZipError(&'a str),
+    MissingEntry(&'a str),
+    DecryptError {
+        field: &'a str,
+        message: &'a str,
+    },
+    ParseError {
+        field: &'a str,
+        message: &'a str,
+    },
+    EmptyField(&'a str),
+    StorageError(&'a str),
+    FetchError(&'a str),
+    EncodeError(&'a str),
+    InvalidPayload(&'a str),
+    MissingSign,
+    InvalidSign,
+    Unauthorized,
+    NotFound,
+    NotAcceptable,
+    HttpError(&'a str),
+    UnhandledWebhook {
+        r#type: &'a str,
+        action: &'a str,
+    },
+    RequestCompleted,
+    WebhookHandled,
+    FileFetched,
+    RecordIndexSkipped(&'a str),
+    SummarySkipped(&'a str),
+    AuditFailed(&'a str),
+    RequestFailed {
+        request_id: &'a str,
+        error: &'a str,
+    },
+    ConfigReadFailed {
+        path: &'a str,
+        error: &'a str,
+    },
+    ConfigParseFailed {
+        path: &'a str,
+        error: &'a str,
+    },
+    ConfigFieldEmpty(&'a str),
+    ConfigNotPositive(&'a str),
+    ConfigFieldInvalid {
+        field: &'a str,
+        error: &'a str,
+    },
+    TemplateMissingPlaceholder(&'a str),
+    HttpClientFailed(&'a str),
+    TlsLoadFailed(&'a str),
+    TlsReloaded,
+    ConfigReloaded(&'a str),
+    ConfigUnchanged,
+    ConfigReloadFailed(&'a str),
+    ConfigRestartRequired(&'a str),
+    StorageOpenFailed(&'a str),
+    BindFailed {
+        addr: &'a str,
+        error: &'a str,
+    },
+    Listening(&'a str),
+    SignalHandlerFailed(&'a str),
+    ShuttingDown,
+    DrainTimedOut(u64),
+    ShutdownComplete,
+    BlobsCollected(usize),
+    ExpiredSwept(u64),
+    ServerError(&'a str),
+    EnvMissing(&'a str),
+    EnvInvalid {
+        name: &'a str,
+        value: &'a str,
+    },
+    FileReadFailed {
+        path: &'a str,
+        error: &'a str,
+    },
+    FileWriteFailed {
+        path: &'a str,
+        error: &'a str,
+    },
+    SaveImported {
+        openid: &'a str,
+        size: usize,
+    },
+    SaveExported {
+        path: &'a str,
+        size: usize,
+    },
+    DumpIoError(&'a str),
+    DumpInvalidLine {
+        line: u64,
+        error: &'a str,
+    },
+    DumpUnsupported(&'a str),
+    DumpTruncated,
+    DumpHashMismatch(&'a str),
+    DumpExported {
+        records: u64,
+        blobs: u64,
+    },
+    DumpImported {
+        records: u64,
+        blobs: u64,
+    },
+    SnapshotRedbOnly,
+    SnapshotCreated {
+        path: &'a str,
+        records: u64,
+        blobs: usize,
+    },
+    SnapshotsPruned(usize),
+    SnapshotVerified {
+        path: &'a str,
+        records: u64,
+    },
+    SnapshotMissingBlobs(usize),
+    SnapshotRestored(&'a str),
+    SnapshotBackedUp(&'a str),
+    SnapshotDatabaseInUse(&'a str),
+    SnapshotBlobsRestored(usize),
+    RecordIndexRebuilt {
+        users: usize,
+        records: usize,
+        skipped: usize,
+    },
+    RecordIndexSkippedUser {
+        openid: &'a str,
+        error: &'a str,
+    },
+    RetentionExpired,
+    RetentionReport {
+        dry_run: bool,
+        expired: usize,
+        trimmed: usize,
+        freed_blobs: usize,
+        freed_bytes: u64,
+    },
+}
+
+impl Msg<'_> {
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::ZhCN => self.zh_cn(),
+            Lang::En => self.en(),
+        }
+    }
+
+    fn zh_cn(&self) -> String {
+        match self {
+            Msg::ZipError(e) => format!("读取存档压缩包失败: {}", e),
+            Msg::MissingEntry(entry) => format!("存档压缩包缺少条目 '{}'", entry),
+            Msg::DecryptError { field, message } => {
+                format!("字段 '{}': 解密失败: {}", field, message)
+            }
+            Msg::ParseError { field, message } => {
+                format!("字段 '{}': 解析失败: {}", field, message)
+            }
+            Msg::EmptyField(field) => format!("字段 '{}': 数据为空", field),
+            Msg::StorageError(e) => format!("存储错误: {}", e),
+            Msg::FetchError(e) => format!("获取文件失败: {}", e),
+            Msg::EncodeError(e) => format!("响应编码失败: {}", e),
+            Msg::InvalidPayload(e) => format!("无效的请求体: {}", e),
+            Msg::MissingSign => "缺少 X-Sign 请求头".to_owned(),
+            Msg::InvalidSign => "X-Sign 校验失败".to_owned(),
+            Msg::Unauthorized => "缺少或错误的管理 token".to_owned(),
+            Msg::NotFound => "未找到".to_owned(),
+            Msg::NotAcceptable => "不支持请求的任何媒体类型".to_owned(),
+            Msg::HttpError(reason) => format!("请求失败: {}", reason),
+            Msg::UnhandledWebhook { r#type, action } => {
+                format!("未处理的 WebHook: type={}, action={}", r#type, action)
+            }
+            Msg::RequestCompleted => "请求处理完成".to_owned(),
+            Msg::WebhookHandled => "WebHook 处理完成".to_owned(),
+            Msg::FileFetched => "存档文件获取完成".to_owned(),
+            Msg::RecordIndexSkipped(e) => format!("存档无法解码, 未更新成绩索引: {}", e),
+            Msg::SummarySkipped(e) => format!("存档摘要无法解码, 未保存: {}", e),
+            Msg::AuditFailed(e) => format!("写入审计记录失败: {}", e),
+            Msg::RequestFailed { request_id, error } => {
+                format!("请求 {} 处理失败: {}", request_id, error)
+            }
+            Msg::ConfigReadFailed { path, error } => {
+                format!("读取配置文件 '{}' 失败: {}", path, error)
+            }
+            Msg::ConfigParseFailed { path, error } => {
+                format!("解析配置文件 '{}' 失败: {}", path, error)
+            }
+            Msg::ConfigFieldEmpty(field) => format!("配置项 {} 不能为空", field),
+            Msg::ConfigNotPositive(field) => format!("配置项 {} 必须大于 0", field),
+            Msg::ConfigFieldInvalid { field, error } => {
+                format!("配置项 {} 无效: {}", field, error)
+            }
+            Msg::TemplateMissingPlaceholder(template) => {
+                format!(
+                    "file_url_template '{}' 缺少 {{file_obj_id}} 占位符",
+                    template
+                )
+            }
+            Msg::HttpClientFailed(e) => format!("创建 HTTP 客户端失败: {}", e),
+            Msg::TlsLoadFailed(e) => format!("加载 TLS 证书失败: {}", e),
+            Msg::TlsReloaded => "TLS 证书已重新加载".to_owned(),
+            Msg::ConfigReloaded(changes) => format!("配置已重新加载: {}", changes),
+            Msg::ConfigUnchanged => "配置已重新加载, 没有变化".to_owned(),
+            Msg::ConfigReloadFailed(e) => format!("重新加载配置失败, 继续使用旧配置: {}", e),
+            Msg::ConfigRestartRequired(fields) => {
+                format!("以下配置项需要重启才能生效: {}", fields)
+            }
+            Msg::StorageOpenFailed(e) => format!("打开存储失败: {}", e),
+            Msg::BindFailed { addr, error } => format!("监听 {} 失败: {}", addr, error),
+            Msg::Listening(addr) => format!("正在监听 {}", addr),
+            Msg::SignalHandlerFailed(e) => format!("注册信号处理器失败: {}", e),
+            Msg::ShuttingDown => "正在关闭...".to_owned(),
+            Msg::DrainTimedOut(secs) => {
+                format!("{} 秒内仍有请求未完成, 强制关闭", secs)
+            }
+            Msg::ShutdownComplete => "已关闭".to_owned(),
+            Msg::BlobsCollected(count) => format!("已回收 {} 个未被引用的存档文件", count),
+            Msg::ExpiredSwept(count) => format!("已清除 {} 条过期记录", count),
+            Msg::ServerError(e) => format!("服务器错误: {}", e),
+            Msg::EnvMissing(name) => format!("环境变量 {} 获取失败", name),
+            Msg::EnvInvalid { name, value } => {
+                format!("环境变量 {} 的值 '{}' 无效", name, value)
+            }
+            Msg::FileReadFailed { path, error } => {
+                format!("读取文件 '{}' 失败: {}", path, error)
+            }
+            Msg::FileWriteFailed { path, error } => {
+                format!("写入文件 '{}' 失败: {}", path, error)
+            }
+            Msg::SaveImported { openid, size } => {
+                format!("已为 {} 导入存档 ({} 字节)", openid, size)
+            }
+            Msg::SaveExported { path, size } => {
+                format!("已导出存档到 '{}' ({} 字节)", path, size)
+            }
+            Msg::DumpIoError(e) => format!("读写导出文件失败: {}", e),
+            Msg::DumpInvalidLine { line, error } => {
+                format!("导出文件第 {} 行无效: {}", line, error)
+            }
+            Msg::DumpUnsupported(header) => format!("不支持的导出格式: {}", header),
+            Msg::DumpTruncated => "导出文件不完整, 缺少结束标记".to_owned(),
+            Msg::DumpHashMismatch(hash) => format!("blob {} 的内容与哈希不符", hash),
+            Msg::DumpExported { records, blobs } => {
+                format!("已导出 {} 条记录与 {} 个存档文件", records, blobs)
+            }
+            Msg::DumpImported { records, blobs } => {
+                format!("已导入 {} 条记录与 {} 个存档文件", records, blobs)
+            }
+            Msg::SnapshotRedbOnly => "快照仅支持 redb 后端".to_owned(),
+            Msg::SnapshotCreated {
+                path,
+                records,
+                blobs,
+            } => format!(
+                "已创建快照 '{}' ({} 条记录, {} 个存档文件)",
+                path, records, blobs
+            ),
+            Msg::SnapshotsPruned(count) => format!("已删除 {} 个过期快照", count),
+            Msg::SnapshotVerified { path, records } => {
+                format!("快照 '{}' 校验通过 ({} 条记录)", path, records)
+            }
+            Msg::SnapshotMissingBlobs(count) => format!(
+                "快照引用的 {} 个存档文件已不存在, 使用 --force 仍然恢复",
+                count
+            ),
+            Msg::SnapshotRestored(path) => format!("已从快照 '{}' 恢复数据库", path),
+            Msg::SnapshotBackedUp(path) => format!("原数据库已保留为 '{}'", path),
+            Msg::SnapshotDatabaseInUse(path) => {
+                format!("数据库 '{}' 正在被其他进程使用, 请先停止服务", path)
+            }
+            Msg::SnapshotBlobsRestored(count) => {
+                format!("已从快照恢复 {} 个已被回收的存档文件", count)
+            }
+            Msg::RecordIndexRebuilt {
+                users,
+                records,
+                skipped,
+            } => format!(
+                "已为 {} 个用户重建 {} 条成绩索引, 跳过 {} 个无法解码的存档",
+                users, records, skipped
+            ),
+            Msg::RecordIndexSkippedUser { openid, error } => {
+                format!("跳过 {} 的存档: {}", openid, error)
+            }
+            Msg::RetentionExpired => "存档已过期, 将被删除".to_owned(),
+            Msg::RetentionReport {
+                dry_run: true,
+                expired,
+                trimmed,
+                freed_blobs,
+                freed_bytes,
+            } => format!(
+                "试运行: 将删除 {} 个过期存档与 {} 个多余的历史存档, 释放 {} 个存档文件 ({} 字节)",
+                expired, trimmed, freed_blobs, freed_bytes
+            ),
+            Msg::RetentionReport {
+                dry_run: false,
+                expired,
+                trimmed,
+                freed_blobs,
+                freed_bytes,
+            } => format!(
+                "已删除 {} 个过期存档与 {} 个多余的历史存档, 释放 {} 个存档文件 ({} 字节)",
+                expired, trimmed, freed_blobs, freed_bytes
+            ),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Msg::ZipError(e) => format!("Failed to read save archive: {}", e),
+            Msg::MissingEntry(entry) => format!("Save archive is missing entry '{}'", entry),
+            Msg::DecryptError { field, message } => {
+                format!("Field '{}': failed to decrypt: {}", field, message)
+            }
+            Msg::ParseError { field, message } => {
+                format!("Field '{}': failed to parse: {}", field, message)
+            }
+            Msg::EmptyField(field) => format!("Field '{}': empty data", field),
+            Msg::StorageError(e) => format!("Storage error: {}", e),
+            Msg::FetchError(e) => format!("Failed to fetch file: {}", e),
+            Msg::EncodeError(e) => format!("Failed to encode response: {}", e),
+            Msg::InvalidPayload(e) => format!("Invalid payload: {}", e),
+            Msg::MissingSign => "Missing X-Sign header".to_owned(),
+            Msg::InvalidSign => "Invalid X-Sign header".to_owned(),
+            Msg::Unauthorized => "Missing or invalid admin token".to_owned(),
+            Msg::NotFound => "Not found".to_owned(),
+            Msg::NotAcceptable => "None of the requested media types is supported".to_owned(),
+            Msg::HttpError(reason) => format!("Request failed: {}", reason),
+            Msg::UnhandledWebhook { r#type, action } => {
+                format!("Unhandled webhook: type={}, action={}", r#type, action)
+            }
+            Msg::RequestCompleted => "Request completed".to_owned(),
+            Msg::WebhookHandled => "Webhook handled".to_owned(),
+            Msg::FileFetched => "Save file fetched".to_owned(),
+            Msg::RecordIndexSkipped(e) => {
+                format!("Save could not be decoded, record index not updated: {}", e)
+            }
+            Msg::AuditFailed(e) => format!("Failed to write the audit entry: {}", e),
+            Msg::SummarySkipped(e) => {
+                format!(
+                    "Save summary could not be decoded and was not stored: {}",
+                    e
+                )
+            }
+            Msg::RequestFailed { request_id, error } => {
+                format!("Request {} failed: {}", request_id, error)
+            }
+            Msg::ConfigReadFailed { path, error } => {
+                format!("Failed to read config file '{}': {}", path, error)
+            }
+            Msg::ConfigParseFailed { path, error } => {
+                format!("Failed to parse config file '{}': {}", path, error)
+            }
+            Msg::ConfigFieldEmpty(field) => format!("Config field {} must not be empty", field),
+            Msg::ConfigNotPositive(field) => {
+                format!("Config field {} must be greater than 0", field)
+            }
+            Msg::ConfigFieldInvalid { field, error } => {
+                format!("Config field {} is invalid: {}", field, error)
+            }
+            Msg::TemplateMissingPlaceholder(template) => format!(
+                "file_url_template '{}' is missing the {{file_obj_id}} placeholder",
+                template
+            ),
+            Msg::HttpClientFailed(e) => format!("Failed to build HTTP client: {}", e),
+            Msg::TlsLoadFailed(e) => format!("Failed to load TLS certificate: {}", e),
+            Msg::TlsReloaded => "TLS certificate reloaded".to_owned(),
+            Msg::ConfigReloaded(changes) => format!("Config reloaded: {}", changes),
+            Msg::ConfigUnchanged => "Config reloaded, nothing changed".to_owned(),
+            Msg::ConfigReloadFailed(e) => {
+                format!("Failed to reload config, keeping the old one: {}", e)
+            }
+            Msg::ConfigRestartRequired(fields) => {
+                format!(
+                    "These settings only take effect after a restart: {}",
+                    fields
+                )
+            }
+            Msg::StorageOpenFailed(e) => format!("Failed to open storage: {}", e),
+            Msg::BindFailed { addr, error } => format!("Failed to bind {}: {}", addr, error),
+            Msg::Listening(addr) => format!("Listening on {}", addr),
+            Msg::SignalHandlerFailed(e) => format!("Failed to install signal handler: {}", e),
+            Msg::ShuttingDown => "Shutting down...".to_owned(),
+            Msg::DrainTimedOut(secs) => {
+                format!("Requests still in flight after {}s, forcing shutdown", secs)
+            }
+            Msg::ShutdownComplete => "Shutdown complete".to_owned(),
+            Msg::BlobsCollected(count) => format!("Removed {} unreferenced save files", count),
+            Msg::ExpiredSwept(count) => format!("Removed {} expired records", count),
+            Msg::ServerError(e) => format!("Server error: {}", e),
+            Msg::EnvMissing(name) => format!("Environment variable {} is not set", name),
+            Msg::EnvInvalid { name, value } => {
+                format!(
+                    "Environment variable {} has invalid value '{}'",
+                    name, value
+                )
+            }
+            Msg::FileReadFailed { path, error } => {
+                format!("Failed to read file '{}': {}", path, error)
+            }
+            Msg::FileWriteFailed { path, error } => {
+                format!("Failed to write file '{}': {}", path, error)
+            }
+            Msg::SaveImported { openid, size } => {
+                format!("Imported save for {} ({} bytes)", openid, size)
+            }
+            Msg::SaveExported { path, size } => {
+                format!("Exported save to '{}' ({} bytes)", path, size)
+            }
+            Msg::DumpIoError(e) => format!("Failed to read or write the dump: {}", e),
+            Msg::DumpInvalidLine { line, error } => {
+                format!("Invalid dump line {}: {}", line, error)
+            }
+            Msg::DumpUnsupported(header) => format!("Unsupported dump format: {}", header),
+            Msg::DumpTruncated => "Dump is incomplete, the end marker is missing".to_owned(),
+            Msg::DumpHashMismatch(hash) => format!("Blob {} does not match its hash", hash),
+            Msg::DumpExported { records, blobs } => {
+                format!("Exported {} records and {} save files", records, blobs)
+            }
+            Msg::DumpImported { records, blobs } => {
+                format!("Imported {} records and {} save files", records, blobs)
+            }
+            Msg::SnapshotRedbOnly => "Snapshots are only supported by the redb backend".to_owned(),
+            Msg::SnapshotCreated {
+                path,
+                records,
+                blobs,
+            } => format!(
+                "Created snapshot '{}' ({} records, {} save files)",
+                path, records, blobs
+            ),
+            Msg::SnapshotsPruned(count) => format!("Removed {} old snapshots", count),
+            Msg::SnapshotVerified { path, records } => {
+                format!("Snapshot '{}' is valid ({} records)", path, records)
+            }
+            Msg::SnapshotMissingBlobs(count) => format!(
+                "{} save files referenced by the snapshot no longer exist, use --force to restore anyway",
+                count
+            ),
+            Msg::SnapshotRestored(path) => {
+                format!("Restored the database from snapshot '{}'", path)
+            }
+            Msg::SnapshotBackedUp(path) => format!("The previous database was kept as '{}'", path),
+            Msg::SnapshotDatabaseInUse(path) => format!(
+                "Database '{}' is in use by another process, stop the server first",
+                path
+            ),
+            Msg::SnapshotBlobsRestored(count) => {
+                format!("Restored {} collected save files from the snapshot", count)
+            }
+            Msg::RecordIndexRebuilt {
+                users,
+                records,
+                skipped,
+            } => format!(
+                "Rebuilt {} record index entries for {} users, skipped {} undecodable saves",
+                records, users, skipped
+            ),
+            Msg::RecordIndexSkippedUser { openid, error } => {
+                format!("Skipped the save of {}: {}", openid, error)
+            }
+            Msg::RetentionExpired => "Save expired and will be deleted".to_owned(),
+            Msg::RetentionReport {
+                dry_run: true,
+                expired,
+                trimmed,
+                freed_blobs,
+                freed_bytes,
+            } => format!(
+                "Dry run: would delete {} expired saves and {} excess history entries, freeing {} save files ({} bytes)",
+                expired, trimmed, freed_blobs, freed_bytes
+            ),
+            Msg::RetentionReport {
+                dry_run: false,
+                expired,
+                trimmed,
+                freed_blobs,
+                freed_bytes,
+            } => format!(
+                "Deleted {} expired saves and {} excess history entries, freeing {} save files ({} bytes)",
+                expired, trimmed, freed_blobs, freed_bytes
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Msg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text(Lang::En))
+    }
+}
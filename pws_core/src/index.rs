@@ -0,0 +1,136 @@
+//! 按谱面汇总的成绩索引, 收到存档时更新, 跨玩家查询时不必逐个解码存档.
+//!
+//! [`RECORD_INDEX_TABLE`] 的键为 openid, 值为该玩家全部谱面的 [`Records`],
+//! 每次写入存档只需写入一次, 不随谱面数量增加.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::save::{self, Save};
+use crate::store::{self, SAVE_META_TABLE, SAVE_TABLE};
+use crate::types::{KVStorage, KVTable};
+
+pub const RECORD_INDEX_TABLE: &str = "record_index";
+
+/// 单个谱面的成绩
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChartRecord {
+    pub score: u32,
+    pub acc: f32,
+    pub fc: bool,
+}
+
+impl ChartRecord {
+    /// 满分即 AP
+    pub fn is_ap(&self) -> bool {
+        self.score == 1_000_000
+    }
+}
+
+/// 一个玩家的全部成绩, 依次以曲目与难度为键
+pub type Records = BTreeMap<String, BTreeMap<String, ChartRecord>>;
+
+fn encode_json<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(value).map_err(|e| Error::Encode(e.to_string()))
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(raw: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(raw).map_err(|e| Error::Storage(e.to_string()))
+}
+
+/// 存档中每个已解锁难度的成绩
+pub fn records(save: &Save) -> Result<Records, Error> {
+    // SerializableGameRecord 的内部映射不公开, 经 JSON 取出
+    let value =
+        serde_json::to_value(&save.game_record).map_err(|e| Error::Encode(e.to_string()))?;
+    serde_json::from_value(value).map_err(|e| Error::Encode(e.to_string()))
+}
+
+fn count(records: &Records) -> usize {
+    records.values().map(BTreeMap::len).sum()
+}
+
+/// 用 `save` 中的成绩替换 `openid` 原有的索引, 返回谱面数
+pub async fn update<KV: KVStorage>(kv: &KV, openid: &str, save: &Save) -> Result<usize, Error> {
+    let records = records(save)?;
+    kv.open_table(RECORD_INDEX_TABLE)
+        .await?
+        .put(openid, &encode_json(&records)?)
+        .await?;
+    Ok(count(&records))
+}
+
+/// 删除 `openid` 的索引
+pub async fn remove<KV: KVStorage>(kv: &KV, openid: &str) -> Result<(), Error> {
+    kv.open_table(RECORD_INDEX_TABLE)
+        .await?
+        .delete(openid)
+        .await
+}
+
+/// 某个谱面上全部玩家的成绩, 按 openid 排序. 需要读取每个玩家的索引.
+pub async fn chart_records<KV: KVStorage>(
+    kv: &KV,
+    chart: &str,
+    difficulty: &str,
+) -> Result<Vec<(String, ChartRecord)>, Error> {
+    let index = kv.open_table(RECORD_INDEX_TABLE).await?;
+    let mut found = Vec::new();
+    for openid in index.list("").await? {
+        let Some(raw) = index.get(&openid).await? else {
+            continue;
+        };
+        let mut records: Records = decode_json(&raw)?;
+        if let Some(record) = records
+            .get_mut(chart)
+            .and_then(|levels| levels.remove(difficulty))
+        {
+            found.push((openid, record));
+        }
+    }
+    Ok(found)
+}
+
+/// [`rebuild`] 的结果
+#[derive(Debug, Default)]
+pub struct Rebuilt {
+    pub users: usize,
+    pub records: usize,
+    /// 存档无法解码而跳过的 openid
+    pub skipped: Vec<(String, save::Error)>,
+}
+
+/// 从已存储的全部存档重新生成索引, 没有存档或存档无法解码的 openid 的索引被删除
+pub async fn rebuild<KV: KVStorage>(kv: &KV) -> Result<Rebuilt, Error> {
+    // 旧版本的存档只在 `save` 表中, 没有元数据
+    let mut openids = BTreeSet::new();
+    for table in [SAVE_META_TABLE, SAVE_TABLE] {
+        openids.extend(kv.open_table(table).await?.list("").await?);
+    }
+
+    let mut rebuilt = Rebuilt::default();
+    let mut indexed = BTreeSet::new();
+    for openid in openids {
+        let Some(data) = store::get_save(kv, &openid).await? else {
+            continue;
+        };
+        match save::decode(&data) {
+            Ok(save) => {
+                rebuilt.records += update(kv, &openid, &save).await?;
+                rebuilt.users += 1;
+                indexed.insert(openid);
+            }
+            Err(e) => rebuilt.skipped.push((openid, e)),
+        }
+    }
+
+    let index = kv.open_table(RECORD_INDEX_TABLE).await?;
+    for openid in index.list("").await? {
+        if !indexed.contains(&openid) {
+            index.delete(&openid).await?;
+        }
+    }
+    Ok(rebuilt)
+}
@@ -1,4 +1,28 @@
+#[cfg(feature = "http")]
+pub mod audit;
+#[cfg(feature = "http")]
+pub mod dump;
+#[cfg(feature = "http")]
+pub mod error;
+pub mod i18n;
+#[cfg(feature = "http")]
+pub mod index;
+#[cfg(feature = "http")]
+pub mod metrics;
+#[cfg(feature = "http")]
 pub mod middleware;
+#[cfg(feature = "http")]
+pub mod retention;
+#[cfg(feature = "http")]
 pub mod routes;
+pub mod save;
+pub mod secret;
+#[cfg(feature = "http")]
+pub mod sign;
+#[cfg(feature = "http")]
+pub mod store;
+#[cfg(feature = "test-support")]
+pub mod testing;
+#[cfg(feature = "http")]
 pub mod types;
 mod utils;
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::types::{AppState, AppUtils, KVStorage};
+
+/// 由 `AppUtils::record` 接收的业务指标, 平台自行决定如何汇总
+#[derive(Debug, Clone, Copy)]
+pub enum Metric<'a> {
+    Webhook {
+        r#type: &'a str,
+        action: &'a str,
+        outcome: &'a str,
+    },
+    SignFailure {
+        reason: &'a str,
+    },
+    ParseFailure {
+        field: &'a str,
+    },
+    InfoRequest {
+        route: &'a str,
+        status: u16,
+    },
+}
+
+pub async fn track_info<U, KV>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    req: Request,
+    next: Next,
+) -> Response
+where
+    U: AppUtils,
+    KV: KVStorage,
+{
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_default();
+
+    let resp = next.run(req).await;
+
+    state.utils.record(Metric::InfoRequest {
+        route: &route,
+        status: resp.status().as_u16(),
+    });
+
+    resp
+}
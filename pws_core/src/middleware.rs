@@ -1,39 +1,164 @@
 use std::sync::Arc;
 
+use crate::error::Error;
+use crate::i18n::{Lang, Msg};
+use crate::metrics::Metric;
 use crate::types::{AppState, AppUtils, KVStorage};
 use crate::utils::constant_time_eq;
-use axum::body::to_bytes;
-use axum::extract::State;
-use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
+use axum::body::Bytes;
+use axum::extract::{FromRequest, State};
+use axum::http::header::{ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderValue, StatusCode};
+use axum::{extract::Request, middleware::Next, response::Response};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+pub async fn request_id<U, KV>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    mut req: Request,
+    next: Next,
+) -> Response
+where
+    U: AppUtils,
+    KV: KVStorage,
+{
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_owned)
+        .unwrap_or_else(|| state.utils.request_id());
+
+    let lang = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Lang::from_accept_language)
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    req.extensions_mut().insert(lang);
+
+    let start = state.utils.now();
+    let resp = next.run(req).instrument(span.clone()).await;
+
+    let error = match resp.extensions().get::<Error>() {
+        Some(err) => Some(err.clone()),
+        None if (resp.status().is_client_error() || resp.status().is_server_error())
+            && !resp.headers().contains_key(CONTENT_TYPE) =>
+        {
+            Some(Error::Http(resp.status()))
+        }
+        None => None,
+    };
+
+    let mut resp = match error {
+        Some(err) => {
+            if err.status().is_server_error() {
+                let log_lang = state.utils.log_lang();
+                let msg = Msg::RequestFailed {
+                    request_id: &id,
+                    error: &err.message(log_lang),
+                };
+                tracing::error!(parent: &span, error.code = err.code(), "{}", msg.text(log_lang));
+            }
+            err.to_response(&id, lang)
+        }
+        None => resp,
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let duration_ms = state.utils.now().saturating_sub(start);
+    tracing::info!(
+        parent: &span,
+        status = resp.status().as_u16(),
+        duration_ms,
+        "{}",
+        Msg::RequestCompleted.text(state.utils.log_lang())
+    );
+
+    resp
+}
 
 pub async fn sign_check<U, KV>(
     State(state): State<Arc<AppState<U, KV>>>,
     req: Request,
     next: Next,
-) -> Result<Response, StatusCode>
+) -> Result<Response, Error>
 where
     U: AppUtils,
     KV: KVStorage,
 {
-    let (parts, body) = req.into_parts();
+    let sign_failed = |err: Error| {
+        state
+            .utils
+            .record(Metric::SignFailure { reason: err.code() });
+        err
+    };
 
-    let sign_header = parts
-        .headers
+    let sign_header = req
+        .headers()
         .get("X-Sign")
         .and_then(|v: &axum::http::HeaderValue| v.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .map(str::to_owned)
+        .ok_or_else(|| sign_failed(Error::MissingSign))?;
 
-    let bytes = to_bytes(body, usize::MAX)
+    // 经由 Bytes 提取器读取, 以遵守宿主设置的 DefaultBodyLimit
+    let (parts, body) = req.into_parts();
+    let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), &())
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| match e.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => Error::Http(StatusCode::PAYLOAD_TOO_LARGE),
+            _ => Error::InvalidPayload(e.body_text()),
+        })?;
 
     let sign_local = state.utils.sign(&bytes);
 
     if !constant_time_eq(sign_local.as_bytes(), sign_header.as_bytes()) {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(sign_failed(Error::InvalidSign));
     }
 
     let req = Request::from_parts(parts, bytes.into());
 
     Ok(next.run(req).await)
 }
+
+pub async fn admin_auth<U, KV>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, Error>
+where
+    U: AppUtils,
+    KV: KVStorage,
+{
+    let token = state.utils.admin_token().ok_or(Error::NotFound)?;
+
+    let provided = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(Error::Unauthorized)?;
+
+    if !constant_time_eq(provided.trim().as_bytes(), token.expose().as_bytes()) {
+        return Err(Error::Unauthorized);
+    }
+
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,131 @@
+//! 数据保留策略: 删除长期未更新的存档, 并限制每个用户的历史存档数量.
+//! 不再被任何存档引用的 blob 随后一并删除.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::i18n::{Lang, Msg};
+use crate::store::{self, SAVE_META_TABLE};
+use crate::types::{KVStorage, KVTable};
+
+const DAY_MS: u64 = 24 * 3600 * 1000;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// 超过这么多天未更新的存档会被删除, 为 `None` 时不按时间清理
+    pub max_age_days: Option<u64>,
+    /// 每个用户保留的历史存档数量, 为 0 时不保留
+    pub keep_history: usize,
+}
+
+/// 过期的存档
+#[derive(Serialize, Clone, Debug)]
+pub struct Expired {
+    pub openid: String,
+    pub updated_at: u64,
+}
+
+/// 一次执行的结果; 试运行时只统计, 不做改动
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Report {
+    pub dry_run: bool,
+    pub expired: Vec<Expired>,
+    /// 超出数量而删除的历史存档数
+    pub trimmed: usize,
+    /// 不再被引用而删除的 blob 数与总字节数
+    pub freed_blobs: usize,
+    pub freed_bytes: u64,
+}
+
+impl Report {
+    /// 记录汇总; 试运行时逐条列出将被删除的存档
+    pub fn log(&self, lang: Lang) {
+        let summary = Msg::RetentionReport {
+            dry_run: self.dry_run,
+            expired: self.expired.len(),
+            trimmed: self.trimmed,
+            freed_blobs: self.freed_blobs,
+            freed_bytes: self.freed_bytes,
+        };
+        if self.expired.is_empty() && self.trimmed == 0 {
+            tracing::debug!("{}", summary.text(lang));
+            return;
+        }
+        if self.dry_run {
+            for expired in &self.expired {
+                tracing::info!(
+                    openid = %expired.openid,
+                    updated_at = expired.updated_at,
+                    "{}",
+                    Msg::RetentionExpired.text(lang)
+                );
+            }
+        }
+        tracing::info!("{}", summary.text(lang));
+    }
+}
+
+/// 按 `policy` 清理存档, 再删除因此不再被引用的 blob.
+/// 旧版本只存在 `save` 表中的存档没有更新时间, 不会被清理.
+pub async fn enforce<KV: KVStorage>(
+    kv: &KV,
+    policy: &RetentionPolicy,
+    now: u64,
+    dry_run: bool,
+) -> Result<Report, Error> {
+    let cutoff = policy
+        .max_age_days
+        .map(|days| now.saturating_sub(days.saturating_mul(DAY_MS)));
+    let mut report = Report {
+        dry_run,
+        ..Report::default()
+    };
+    // 被删除的存档引用的 blob 及其大小, 与仍被保留的存档引用的 blob
+    let mut released: HashMap<String, usize> = HashMap::new();
+    let mut kept: HashSet<String> = HashSet::new();
+
+    let table = kv.open_table(SAVE_META_TABLE).await?;
+    for openid in table.list("").await? {
+        let Some(mut meta) = store::get_save_meta(kv, &openid).await? else {
+            continue;
+        };
+        if cutoff.is_some_and(|cutoff| meta.updated_at < cutoff) {
+            if !dry_run {
+                store::delete_save(kv, &openid).await?;
+            }
+            released.extend(meta.blobs().map(|(hash, size)| (hash.to_owned(), size)));
+            report.expired.push(Expired {
+                openid,
+                updated_at: meta.updated_at,
+            });
+            continue;
+        }
+        let trimmed = meta.trim_history(policy.keep_history);
+        if !trimmed.is_empty() {
+            if !dry_run {
+                store::put_save_meta(kv, &openid, &meta).await?;
+            }
+            report.trimmed += trimmed.len();
+            released.extend(trimmed.into_iter().map(|v| (v.hash, v.size)));
+        }
+        kept.extend(meta.blobs().map(|(hash, _)| hash.to_owned()));
+    }
+
+    released.retain(|hash, _| !kept.contains(hash));
+    if !dry_run && !released.is_empty() {
+        // 清理期间收到的存档可能重新引用了相同内容
+        let referenced = store::referenced_blobs(kv).await?;
+        released.retain(|hash, _| !referenced.contains(hash));
+        let deleted: HashSet<String> = store::delete_stale_blobs(kv, released.keys().cloned(), now)
+            .await?
+            .into_iter()
+            .collect();
+        released.retain(|hash, _| deleted.contains(hash));
+    }
+    report.freed_blobs = released.len();
+    report.freed_bytes = released.values().map(|&size| size as u64).sum();
+    Ok(report)
+}
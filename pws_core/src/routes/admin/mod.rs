@@ -0,0 +1,20 @@
+mod users;
+
+use axum::Router;
+use axum::middleware::from_fn_with_state;
+use axum::routing::{get, post};
+use std::sync::Arc;
+
+use crate::middleware::admin_auth;
+use crate::types::{AppState, AppUtils, KVStorage};
+
+pub fn router<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
+    Router::new()
+        .route("/users", get(users::list))
+        .route("/users/{open_id}", get(users::show).delete(users::delete))
+        .route("/users/{open_id}/refetch", post(users::refetch))
+        .route("/users/{open_id}/check", post(users::check))
+        .route("/users/{open_id}/webhooks", get(users::webhooks))
+        .with_state(state.clone())
+        .route_layer(from_fn_with_state(state, admin_auth))
+}
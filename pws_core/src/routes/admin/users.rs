@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{self, AuditEntry};
+use crate::error::Error;
+use crate::i18n::{Lang, Msg};
+use crate::routes::info::utils::decode_save;
+use crate::store::{self, SaveMeta};
+use crate::types::{AppState, AppUtils, KVStorage};
+
+#[derive(Serialize)]
+pub struct UserSummary {
+    openid: String,
+    nickname: String,
+    updated_at: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct UserDetail {
+    openid: String,
+    nickname: Option<String>,
+    updated_at: Option<u64>,
+    save: Option<SaveMeta>,
+}
+
+#[derive(Deserialize)]
+pub struct Refetch {
+    file_object_id: String,
+}
+
+#[derive(Serialize)]
+pub struct DecodeCheck {
+    ok: bool,
+    size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+pub async fn list<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+) -> Result<Json<Vec<UserSummary>>, Error> {
+    let users = store::list_users(&state.kv)
+        .await?
+        .into_iter()
+        .map(|(openid, user)| UserSummary {
+            openid,
+            nickname: user.nickname,
+            updated_at: user.updated_at,
+        })
+        .collect();
+    Ok(Json(users))
+}
+
+pub async fn show<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+) -> Result<Json<UserDetail>, Error> {
+    let user = store::get_user(&state.kv, &open_id).await?;
+    let save = store::get_save_meta(&state.kv, &open_id).await?;
+    if user.is_none() && save.is_none() {
+        return Err(Error::NotFound);
+    }
+
+    Ok(Json(UserDetail {
+        openid: open_id,
+        nickname: user.as_ref().map(|u| u.nickname.clone()),
+        updated_at: user.and_then(|u| u.updated_at),
+        save,
+    }))
+}
+
+pub async fn delete<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+) -> Result<StatusCode, Error> {
+    store::delete_user(&state.kv, &open_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 重新拉取存档, 保留已有的摘要, 并在审计记录中记下这次改动
+pub async fn refetch<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+    body: Result<Json<Refetch>, JsonRejection>,
+) -> Result<Json<SaveMeta>, Error> {
+    let Json(body) = body.map_err(|e| Error::InvalidPayload(e.body_text()))?;
+    let received_at = state.utils.now();
+
+    let result = async {
+        let data = state.utils.get_file(&body.file_object_id).await?;
+        // 存档接口之外没有提交摘要的途径, 沿用已有的摘要
+        let summary = store::get_summary(&state.kv, &open_id).await?;
+        store::put_save(
+            &state.kv,
+            &open_id,
+            &body.file_object_id,
+            &data,
+            summary.as_ref(),
+            state.utils.now(),
+            state.utils.retention().keep_history,
+        )
+        .await
+    }
+    .await;
+
+    let lang = state.utils.log_lang();
+    let entry = AuditEntry {
+        received_at,
+        r#type: "admin".to_owned(),
+        action: "refetch".to_owned(),
+        openid: open_id.clone(),
+        file_object_id: Some(body.file_object_id),
+        outcome: match &result {
+            Ok(_) => "ok".to_owned(),
+            Err(err) => err.code().to_owned(),
+        },
+        message: result.as_ref().err().map(|e| e.message(lang)),
+        duration_ms: state.utils.now().saturating_sub(received_at),
+    };
+    // 审计记录写入失败不影响处理结果
+    if let Err(e) = audit::append(&state.kv, &open_id, entry).await {
+        tracing::warn!("{}", Msg::AuditFailed(&e.message(lang)).text(lang));
+    }
+
+    Ok(Json(result?.meta))
+}
+
+pub async fn check<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+    Extension(lang): Extension<Lang>,
+) -> Result<Json<DecodeCheck>, Error> {
+    let save = store::get_save(&state.kv, &open_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let size = save.len();
+
+    let check = match decode_save(&state, save) {
+        Ok(_) => DecodeCheck {
+            ok: true,
+            size,
+            field: None,
+            code: None,
+            message: None,
+        },
+        Err(e) => DecodeCheck {
+            ok: false,
+            size,
+            field: e.field(),
+            code: Some(e.code()),
+            message: Some(e.message(lang)),
+        },
+    };
+    Ok(Json(check))
+}
+
+/// 最近收到的 webhook, 由新到旧
+pub async fn webhooks<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+) -> Result<Json<Vec<AuditEntry>>, Error> {
+    let mut entries = audit::entries(&state.kv, &open_id).await?;
+    entries.reverse();
+    Ok(Json(entries))
+}
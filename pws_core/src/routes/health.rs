@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::i18n::Lang;
+use crate::types::{AppState, AppUtils, KVStorage};
+
+#[derive(Serialize)]
+pub struct Check {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl Check {
+    fn new(result: Result<(), Error>, lang: Lang) -> Self {
+        match result {
+            Ok(()) => Check {
+                ok: true,
+                code: None,
+                message: None,
+            },
+            Err(e) => Check {
+                ok: false,
+                code: Some(e.code()),
+                message: Some(e.message(lang)),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    ready: bool,
+    storage: Check,
+    upstream: Check,
+}
+
+pub async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+pub async fn readyz<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Extension(lang): Extension<Lang>,
+) -> (StatusCode, Json<Readiness>) {
+    let storage = Check::new(state.kv.ready().await, lang);
+    let upstream = Check::new(state.utils.ready().await, lang);
+    let ready = storage.ok && upstream.ok;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            storage,
+            upstream,
+        }),
+    )
+}
+
+pub fn router<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
@@ -1,50 +1,27 @@
-use super::utils::{parse_save, unzip};
-use axum::Json;
-use serde_json::json;
-use std::io::Cursor;
+use super::format::Format;
+use super::utils::load_save;
+use crate::save::Save;
+use serde::Serialize;
 use std::sync::Arc;
 
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-};
+use axum::extract::{Path, State};
+use axum::response::Response;
 
-use crate::types::LogLevel;
-use crate::types::{AppState, AppUtils, KVStorage, KVTable};
+use crate::error::Error;
+use crate::types::{AppState, AppUtils, KVStorage};
+
+#[derive(Serialize)]
+struct All {
+    nickname: String,
+    save: Save,
+}
 
 pub async fn handler<U: AppUtils, KV: KVStorage>(
     State(state): State<Arc<AppState<U, KV>>>,
     Path(open_id): Path<String>,
-) -> axum::response::Response {
-    let save: Cursor<Vec<u8>> = match state.kv.open_table("save").await.get(&open_id).await {
-        Some(v) => Cursor::new(v),
-        None => return StatusCode::NOT_FOUND.into_response(),
-    };
-    let nickname = match state.kv.open_table("user").await.get(&open_id).await {
-        Some(v) => String::from_utf8_lossy(&v).into_owned(),
-        None => return StatusCode::NOT_FOUND.into_response(),
-    };
-
-    let zip = match unzip(save) {
-        Ok(z) => z,
-        Err(msg) => {
-            state.utils.logger(LogLevel::ERROR, &msg);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
-
-    let save = match parse_save(zip) {
-        Ok(z) => z,
-        Err(msg) => {
-            state.utils.logger(LogLevel::ERROR, &msg);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    format: Format,
+) -> Result<Response, Error> {
+    let (nickname, save) = load_save(&state, &open_id).await?;
 
-    Json(json!({
-        "nickname": nickname,
-        "save": save
-    }))
-    .into_response()
+    format.render(&All { nickname, save })
 }
@@ -1,20 +1,16 @@
-use super::utils::{parse_save, unzip};
-use axum::Json;
+use super::format::Format;
+use super::utils::load_save;
 use phi_save_codec::game_progress::serde::SerializableMoney;
 use phi_save_codec::game_record::serde::SerializableGameRecord;
 use phi_save_codec::user::serde::SerializableUser;
 use serde::Serialize;
-use std::io::Cursor;
 use std::sync::Arc;
 
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-};
+use axum::extract::{Path, State};
+use axum::response::Response;
 
-use crate::types::LogLevel;
-use crate::types::{AppState, AppUtils, KVStorage, KVTable};
+use crate::error::Error;
+use crate::types::{AppState, AppUtils, KVStorage};
 
 #[derive(Serialize)]
 struct Curated {
@@ -28,38 +24,16 @@ struct Curated {
 pub async fn handler<U: AppUtils, KV: KVStorage>(
     State(state): State<Arc<AppState<U, KV>>>,
     Path(open_id): Path<String>,
-) -> axum::response::Response {
-    let save: Cursor<Vec<u8>> = match state.kv.open_table("save").await.get(&open_id).await {
-        Some(v) => Cursor::new(v),
-        None => return StatusCode::NOT_FOUND.into_response(),
-    };
-    let nickname = match state.kv.open_table("user").await.get(&open_id).await {
-        Some(v) => String::from_utf8_lossy(&v).into_owned(),
-        None => return StatusCode::NOT_FOUND.into_response(),
-    };
-
-    let zip = match unzip(save) {
-        Ok(z) => z,
-        Err(msg) => {
-            state.utils.logger(LogLevel::ERROR, &msg);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
-
-    let save = match parse_save(zip) {
-        Ok(z) => z,
-        Err(msg) => {
-            state.utils.logger(LogLevel::ERROR, &msg);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    format: Format,
+) -> Result<Response, Error> {
+    let (nickname, save) = load_save(&state, &open_id).await?;
 
     let curated = Curated {
-        nickname: nickname,
+        nickname,
         device_name: save.settings.device_name,
         money: save.game_progress.money,
         record: save.game_record,
         user: save.user,
     };
-    Json(curated).into_response()
+    format.render(&curated)
 }
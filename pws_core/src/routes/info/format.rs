@@ -0,0 +1,106 @@
+use axum::extract::FromRequestParts;
+use axum::http::HeaderValue;
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::utils::quality_values;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+    MsgPack,
+}
+
+impl Format {
+    /// 通配符按 JSON、CBOR、MessagePack 的顺序匹配全部格式
+    const ALL: [Format; 3] = [Format::Json, Format::Cbor, Format::MsgPack];
+
+    fn from_media_type(media_type: &str) -> &'static [Self] {
+        match media_type {
+            "application/*" | "*/*" => &Self::ALL,
+            "application/json" => &[Format::Json],
+            "application/cbor" => &[Format::Cbor],
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                &[Format::MsgPack]
+            }
+            _ => &[],
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Cbor => "application/cbor",
+            Format::MsgPack => "application/msgpack",
+        }
+    }
+
+    /// 按 `Accept` 选出 q 值最高且受支持的格式, 未携带 `Accept` 时默认 JSON.
+    /// 明确以 `q=0` 排除的格式不会再被通配符选中.
+    pub fn negotiate(accept: Option<&str>) -> Option<Self> {
+        let Some(accept) = accept.filter(|v| !v.trim().is_empty()) else {
+            return Some(Format::Json);
+        };
+
+        let ranges: Vec<(&[Format], f32)> = quality_values(accept)
+            .map(|(media_type, q)| (Self::from_media_type(&media_type.to_ascii_lowercase()), q))
+            .collect();
+        let excluded: Vec<Format> = ranges
+            .iter()
+            .filter(|(formats, q)| *q == 0.0 && formats.len() == 1)
+            .map(|(formats, _)| formats[0])
+            .collect();
+
+        let mut best: Option<(Format, f32)> = None;
+
+        for (formats, q) in ranges {
+            if q == 0.0 {
+                continue;
+            }
+
+            if let Some(&format) = formats.iter().find(|f| !excluded.contains(f))
+                && best.is_none_or(|(_, best_q)| q > best_q)
+            {
+                best = Some((format, q));
+            }
+        }
+
+        best.map(|(format, _)| format)
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Error> {
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(|e| Error::Encode(e.to_string())),
+            Format::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf).map_err(|e| Error::Encode(e.to_string()))?;
+                Ok(buf)
+            }
+            Format::MsgPack => {
+                rmp_serde::to_vec_named(value).map_err(|e| Error::Encode(e.to_string()))
+            }
+        }
+    }
+
+    pub fn render<T: Serialize>(self, value: &T) -> Result<Response, Error> {
+        let body = self.encode(value)?;
+        Ok((
+            [(CONTENT_TYPE, HeaderValue::from_static(self.content_type()))],
+            body,
+        )
+            .into_response())
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Format {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept = parts.headers.get(ACCEPT).and_then(|v| v.to_str().ok());
+        Format::negotiate(accept).ok_or(Error::NotAcceptable)
+    }
+}
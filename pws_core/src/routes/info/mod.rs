@@ -1,16 +1,22 @@
 mod all;
 mod curated;
-mod utils;
+mod format;
+mod summary;
+pub(super) mod utils;
 
 use axum::Router;
+use axum::middleware::from_fn_with_state;
 use axum::routing::get;
 use std::sync::Arc;
 
+use crate::metrics::track_info;
 use crate::types::{AppState, AppUtils, KVStorage};
 
 pub fn router<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
     Router::new()
         .route("/{open_id}/all", get(all::handler))
         .route("/{open_id}/curated", get(curated::handler))
+        .route("/{open_id}/summary", get(summary::handler))
+        .route_layer(from_fn_with_state(state.clone(), track_info))
         .with_state(state.clone())
 }
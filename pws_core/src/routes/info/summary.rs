@@ -0,0 +1,36 @@
+use super::format::Format;
+use phi_save_codec::summary::serde::SerializableSummary;
+use serde::Serialize;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::response::Response;
+
+use crate::error::Error;
+use crate::store;
+use crate::types::{AppState, AppUtils, KVStorage};
+
+#[derive(Serialize)]
+struct Summary {
+    nickname: String,
+    summary: SerializableSummary,
+}
+
+/// 收到存档时保存的摘要, 不需要解压和解密存档
+pub async fn handler<U: AppUtils, KV: KVStorage>(
+    State(state): State<Arc<AppState<U, KV>>>,
+    Path(open_id): Path<String>,
+    format: Format,
+) -> Result<Response, Error> {
+    let summary = store::get_summary(&state.kv, &open_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let user = store::get_user(&state.kv, &open_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    format.render(&Summary {
+        nickname: user.nickname,
+        summary,
+    })
+}
@@ -1,12 +1,40 @@
+mod admin;
+mod health;
 mod info;
+mod openapi;
 mod webhook;
 
+use crate::error::Error;
+use crate::middleware::request_id;
 use crate::types::{AppState, AppUtils, KVStorage};
 use axum::Router;
+use axum::middleware::from_fn_with_state;
+use axum::routing::get;
 use std::sync::Arc;
 
+async fn fallback() -> Error {
+    Error::NotFound
+}
+
 pub fn router<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
+    with_request_id(routes(state.clone()), state)
+}
+
+/// 不含 request_id 中间件的全部路由, 宿主可以在其内侧追加超时、限流等 layer
+pub fn routes<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
     Router::new()
         .nest("/webhook", webhook::router(state.clone()))
-        .nest("/info", info::router(state))
+        .nest("/info", info::router(state.clone()))
+        .nest("/admin", admin::router(state.clone()))
+        .route("/openapi.json", get(openapi::handler))
+        .merge(health::router(state))
+        .fallback(fallback)
+}
+
+/// 错误体在这一层渲染, 因此它必须位于所有可能产生错误响应的 layer 之外
+pub fn with_request_id<U: AppUtils, KV: KVStorage>(
+    router: Router,
+    state: Arc<AppState<U, KV>>,
+) -> Router {
+    router.layer(from_fn_with_state(state, request_id))
 }
@@ -0,0 +1,494 @@
+use axum::Json;
+use serde_json::{Value, json};
+
+const INFO_MEDIA_TYPES: &[&str] = &[
+    "application/json",
+    "application/cbor",
+    "application/msgpack",
+];
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn bool_array(len: usize) -> Value {
+    json!({
+        "type": "array",
+        "items": { "type": "boolean" },
+        "minItems": len,
+        "maxItems": len
+    })
+}
+
+fn uint16() -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": 65535 })
+}
+
+fn object(properties: Value) -> Value {
+    let required: Vec<&String> = properties
+        .as_object()
+        .map_or(Vec::new(), |p| p.keys().collect());
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
+}
+
+fn negotiated_content(schema: &str) -> Value {
+    INFO_MEDIA_TYPES
+        .iter()
+        .map(|media_type| {
+            (
+                media_type.to_string(),
+                json!({ "schema": schema_ref(schema) }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema_ref("Error") } }
+    })
+}
+
+fn open_id_param() -> Value {
+    json!({
+        "name": "open_id",
+        "in": "path",
+        "required": true,
+        "description": "TapTap openid",
+        "schema": { "type": "string" }
+    })
+}
+
+fn info_operation(summary: &str, schema: &str) -> Value {
+    json!({
+        "get": {
+            "tags": ["info"],
+            "summary": summary,
+            "parameters": [open_id_param()],
+            "responses": {
+                "200": { "description": "OK", "content": negotiated_content(schema) },
+                "404": error_response("User or save not found"),
+                "406": error_response("None of the types in `Accept` is supported"),
+                "500": error_response("Failed to decode the stored save")
+            }
+        }
+    })
+}
+
+fn admin_operation(summary: &str, response: Value) -> Value {
+    json!({
+        "tags": ["admin"],
+        "summary": summary,
+        "security": [{ "admin": [] }],
+        "parameters": [open_id_param()],
+        "responses": {
+            "200": response,
+            "401": error_response("Missing or invalid bearer token"),
+            "404": error_response("User not found, or the admin API is disabled")
+        }
+    })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+fn paths() -> Value {
+    json!({
+        "/webhook/tcs": {
+            "post": {
+                "tags": ["webhook"],
+                "summary": "Receive a Tap-Cloud-Server webhook",
+                "parameters": [{
+                    "name": "X-Sign",
+                    "in": "header",
+                    "required": true,
+                    "description": "URL-safe base64 of the Blake2s-128 MAC of the raw body, keyed by `sign_key`",
+                    "schema": { "type": "string" }
+                }],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("WebhookPayload") } }
+                },
+                "responses": {
+                    "200": { "description": "Accepted" },
+                    "400": error_response("Malformed payload"),
+                    "401": error_response("Missing or invalid `X-Sign`"),
+                    "500": error_response("Failed to store the save"),
+                    "502": error_response("Failed to fetch the save file")
+                }
+            }
+        },
+        "/info/{open_id}/all": info_operation("Full decoded save", "All"),
+        "/info/{open_id}/curated": info_operation("Curated profile and records", "Curated"),
+        "/info/{open_id}/summary": info_operation("Cloud save summary stored with the save", "Summary"),
+        "/admin/users": {
+            "get": {
+                "tags": ["admin"],
+                "summary": "List stored users",
+                "security": [{ "admin": [] }],
+                "responses": {
+                    "200": {
+                        "description": "OK",
+                        "content": json_content(json!({ "type": "array", "items": schema_ref("UserSummary") }))
+                    },
+                    "401": error_response("Missing or invalid bearer token"),
+                    "404": error_response("The admin API is disabled")
+                }
+            }
+        },
+        "/admin/users/{open_id}": {
+            "get": admin_operation(
+                "Stored user and save metadata",
+                json!({ "description": "OK", "content": json_content(schema_ref("UserDetail")) })
+            ),
+            "delete": {
+                "tags": ["admin"],
+                "summary": "Delete the user, its save, save metadata and webhook audit entries",
+                "security": [{ "admin": [] }],
+                "parameters": [open_id_param()],
+                "responses": {
+                    "204": { "description": "Deleted" },
+                    "401": error_response("Missing or invalid bearer token"),
+                    "404": error_response("The admin API is disabled")
+                }
+            }
+        },
+        "/admin/users/{open_id}/refetch": {
+            "post": {
+                "tags": ["admin"],
+                "summary": "Fetch a save file again and store it",
+                "description": "Updates the record index like a save webhook and keeps the stored summary. The refetch is recorded in the webhook audit entries with type `admin`.",
+                "security": [{ "admin": [] }],
+                "parameters": [open_id_param()],
+                "requestBody": {
+                    "required": true,
+                    "content": json_content(object(json!({ "file_object_id": { "type": "string" } })))
+                },
+                "responses": {
+                    "200": { "description": "Stored", "content": json_content(schema_ref("SaveMeta")) },
+                    "400": error_response("Malformed body"),
+                    "401": error_response("Missing or invalid bearer token"),
+                    "404": error_response("The admin API is disabled"),
+                    "502": error_response("Failed to fetch the save file")
+                }
+            }
+        },
+        "/admin/users/{open_id}/check": {
+            "post": admin_operation(
+                "Decode the stored save and report the first failing field",
+                json!({ "description": "OK", "content": json_content(schema_ref("DecodeCheck")) })
+            )
+        },
+        "/admin/users/{open_id}/webhooks": {
+            "get": admin_operation(
+                "Recently received webhooks for the user, newest first, with secrets redacted",
+                json!({
+                    "description": "OK",
+                    "content": json_content(json!({ "type": "array", "items": schema_ref("AuditEntry") }))
+                })
+            )
+        },
+        "/healthz": {
+            "get": {
+                "tags": ["meta"],
+                "summary": "Liveness probe",
+                "responses": { "200": { "description": "Process is alive" } }
+            }
+        },
+        "/readyz": {
+            "get": {
+                "tags": ["meta"],
+                "summary": "Readiness probe, checks storage and the save file host",
+                "responses": {
+                    "200": {
+                        "description": "Ready",
+                        "content": { "application/json": { "schema": schema_ref("Readiness") } }
+                    },
+                    "503": {
+                        "description": "At least one check failed",
+                        "content": { "application/json": { "schema": schema_ref("Readiness") } }
+                    }
+                }
+            }
+        },
+        "/openapi.json": {
+            "get": {
+                "tags": ["meta"],
+                "summary": "This document",
+                "responses": {
+                    "200": { "description": "OpenAPI 3 document", "content": { "application/json": {} } }
+                }
+            }
+        }
+    })
+}
+
+fn schemas() -> Value {
+    json!({
+        "Error": object(json!({
+            "code": { "type": "string", "examples": ["not_found", "invalid_sign", "parse_error"] },
+            "message": { "type": "string" },
+            "request_id": { "type": "string", "description": "Also returned in the `X-Request-Id` header" }
+        })),
+        "UserSummary": {
+            "type": "object",
+            "properties": {
+                "openid": { "type": "string" },
+                "nickname": { "type": "string" },
+                "updated_at": { "type": ["integer", "null"], "description": "Unix ms, absent for legacy records" }
+            },
+            "required": ["openid", "nickname", "updated_at"]
+        },
+        "UserDetail": {
+            "type": "object",
+            "properties": {
+                "openid": { "type": "string" },
+                "nickname": { "type": ["string", "null"] },
+                "updated_at": { "type": ["integer", "null"] },
+                "save": { "oneOf": [schema_ref("SaveMeta"), { "type": "null" }] }
+            },
+            "required": ["openid", "nickname", "updated_at", "save"]
+        },
+        "SaveMeta": {
+            "type": "object",
+            "properties": {
+                "file_object_id": { "type": "string" },
+                "size": { "type": "integer", "minimum": 0 },
+                "updated_at": { "type": "integer", "description": "Unix ms" },
+                "hash": { "type": "string", "description": "BLAKE2s-256 of the save zip, absent for legacy records" },
+                "history": {
+                    "type": "array",
+                    "description": "Replaced saves, oldest first, absent when empty",
+                    "items": schema_ref("SaveVersion")
+                }
+            },
+            "required": ["file_object_id", "size", "updated_at"]
+        },
+        "SaveVersion": {
+            "type": "object",
+            "properties": {
+                "file_object_id": { "type": "string" },
+                "size": { "type": "integer", "minimum": 0 },
+                "updated_at": { "type": "integer", "description": "Unix ms" },
+                "hash": { "type": "string" }
+            },
+            "required": ["file_object_id", "size", "updated_at", "hash"]
+        },
+        "AuditEntry": {
+            "type": "object",
+            "properties": {
+                "received_at": { "type": "integer", "description": "Unix ms" },
+                "type": { "type": "string", "description": "Webhook `meta.type`, or `admin` for admin operations" },
+                "action": { "type": "string" },
+                "openid": { "type": "string" },
+                "file_object_id": { "type": "string" },
+                "outcome": { "type": "string", "examples": ["ok", "unhandled", "fetch_error"] },
+                "message": { "type": "string", "description": "Error message when the webhook failed" },
+                "duration_ms": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["received_at", "type", "action", "openid", "outcome", "duration_ms"]
+        },
+        "DecodeCheck": {
+            "type": "object",
+            "properties": {
+                "ok": { "type": "boolean" },
+                "size": { "type": "integer", "minimum": 0 },
+                "field": { "type": "string" },
+                "code": { "type": "string" },
+                "message": { "type": "string" }
+            },
+            "required": ["ok", "size"]
+        },
+        "Readiness": object(json!({
+            "ready": { "type": "boolean" },
+            "storage": schema_ref("Check"),
+            "upstream": schema_ref("Check")
+        })),
+        "Check": {
+            "type": "object",
+            "properties": {
+                "ok": { "type": "boolean" },
+                "code": { "type": "string" },
+                "message": { "type": "string" }
+            },
+            "required": ["ok"]
+        },
+        "WebhookPayload": object(json!({
+            "meta": schema_ref("WebhookMeta"),
+            "user": schema_ref("WebhookUser"),
+            "data": {
+                "description": "Event specific data, `SaveEventData` when `meta.type` is `save`",
+                "oneOf": [schema_ref("SaveEventData"), { "type": "object" }]
+            }
+        })),
+        "WebhookMeta": object(json!({
+            "type": { "type": "string", "examples": ["save", "user"] },
+            "action": { "type": "string", "examples": ["create", "update", "login"] }
+        })),
+        "WebhookUser": object(json!({
+            "openid": { "type": "string" },
+            "session_token": { "type": "string" },
+            "nickname": { "type": "string" }
+        })),
+        "SaveEventData": object(json!({
+            "file_object_id": { "type": "string" },
+            "summary": { "type": "string", "description": "Base64 encoded TapTap cloud save summary" }
+        })),
+        "All": object(json!({
+            "nickname": { "type": "string" },
+            "save": schema_ref("Save")
+        })),
+        "Curated": object(json!({
+            "nickname": { "type": "string" },
+            "user": schema_ref("User"),
+            "money": schema_ref("Money"),
+            "device_name": { "type": "string" },
+            "record": schema_ref("GameRecord")
+        })),
+        "Summary": object(json!({
+            "nickname": { "type": "string" },
+            "summary": schema_ref("SaveSummary")
+        })),
+        "SaveSummary": object(json!({
+            "save_version": { "type": "integer", "minimum": 0, "maximum": 255 },
+            "challenge_mode_rank": {
+                "type": "integer",
+                "minimum": 0,
+                "maximum": 65535,
+                "description": "Hundreds digit is the color, the rest is the level"
+            },
+            "rks": { "type": "number" },
+            "game_version": uint16(),
+            "avatar": { "type": "string" },
+            "level": schema_ref("LevelCounts")
+        })),
+        "LevelCounts": object(json!({
+            "ez": schema_ref("LevelCount"),
+            "hd": schema_ref("LevelCount"),
+            "in": schema_ref("LevelCount"),
+            "at": schema_ref("LevelCount")
+        })),
+        "LevelCount": object(json!({
+            "clear": uint16(),
+            "fc": uint16(),
+            "phi": uint16()
+        })),
+        "Save": object(json!({
+            "game_progress": schema_ref("GameProgress"),
+            "game_record": schema_ref("GameRecord"),
+            "user": schema_ref("User"),
+            "game_key": schema_ref("GameKey"),
+            "settings": schema_ref("Settings")
+        })),
+        "GameProgress": object(json!({
+            "base": schema_ref("GameProgressBase"),
+            "completed": { "type": "string" },
+            "song_update_info": uint16(),
+            "challenge_mode_rank": uint16(),
+            "money": schema_ref("Money"),
+            "unlock_flag_of_spasmodic": bool_array(4),
+            "unlock_flag_of_igallta": bool_array(4),
+            "unlock_flag_of_rrharil": bool_array(4),
+            "flag_of_song_record_key": bool_array(8),
+            "random_version_unlocked": bool_array(6),
+            "chapter8_base": schema_ref("Chapter8Base"),
+            "chapter8_song_unlocked": bool_array(6),
+            "flag_of_song_record_key_takumi": bool_array(3)
+        })),
+        "GameProgressBase": object(json!({
+            "is_first_run": { "type": "boolean" },
+            "legacy_chapter_finished": { "type": "boolean" },
+            "already_show_collection_tip": { "type": "boolean" },
+            "already_show_auto_unlock_in_tip": { "type": "boolean" }
+        })),
+        "Chapter8Base": object(json!({
+            "unlock_begin": { "type": "boolean" },
+            "unlock_second_phase": { "type": "boolean" },
+            "passed": { "type": "boolean" }
+        })),
+        "Money": object(json!({
+            "kib": uint16(),
+            "mib": uint16(),
+            "gib": uint16(),
+            "tib": uint16(),
+            "pib": uint16()
+        })),
+        "GameRecord": {
+            "type": "object",
+            "description": "Song id to per difficulty records",
+            "additionalProperties": schema_ref("SongRecord")
+        },
+        "SongRecord": {
+            "type": "object",
+            "propertyNames": { "enum": ["EZ", "HD", "IN", "AT", "Legacy"] },
+            "additionalProperties": schema_ref("LevelRecord")
+        },
+        "LevelRecord": object(json!({
+            "score": { "type": "integer", "minimum": 0 },
+            "acc": { "type": "number" },
+            "fc": { "type": "boolean" }
+        })),
+        "User": object(json!({
+            "show_player_id": { "type": "boolean" },
+            "self_intro": { "type": "string" },
+            "avatar": { "type": "string" },
+            "background": { "type": "string" }
+        })),
+        "GameKey": object(json!({
+            "key_list": { "type": "array", "items": schema_ref("Key") },
+            "lanota_read_keys": bool_array(6),
+            "camellia_read_key": bool_array(8),
+            "side_story4_begin_read_key": { "type": "boolean" },
+            "old_score_cleared_v390": { "type": "boolean" }
+        })),
+        "Key": object(json!({
+            "name": { "type": "string" },
+            "type": bool_array(5),
+            "flag": { "type": "array", "items": { "type": "boolean" } }
+        })),
+        "Settings": object(json!({
+            "base": schema_ref("SettingsBase"),
+            "device_name": { "type": "string" },
+            "bright": { "type": "number" },
+            "music_volume": { "type": "number" },
+            "effect_volume": { "type": "number" },
+            "hit_sound_volume": { "type": "number" },
+            "sound_offset": { "type": "number" },
+            "note_scale": { "type": "number" }
+        })),
+        "SettingsBase": object(json!({
+            "chord_support": { "type": "boolean" },
+            "fc_ap_indicator": { "type": "boolean" },
+            "enable_hit_sound": { "type": "boolean" },
+            "low_resolution_mode": { "type": "boolean" }
+        }))
+    })
+}
+
+pub fn document() -> Value {
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Phi-WebHook-Server",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": paths(),
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "admin": { "type": "http", "scheme": "bearer" }
+            }
+        }
+    })
+}
+
+pub async fn handler() -> Json<Value> {
+    Json(document())
+}
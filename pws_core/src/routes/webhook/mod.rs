@@ -2,62 +2,116 @@ mod save;
 mod user;
 
 use axum::extract::State;
+use axum::extract::rejection::JsonRejection;
 use axum::middleware::from_fn_with_state;
-use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::post};
-use serde::Deserialize;
+use axum::{Json, Router, http::StatusCode, routing::post};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
+use tracing::Instrument;
 
+use crate::audit::{self, AuditEntry};
+use crate::error::Error;
+use crate::i18n::Msg;
+use crate::metrics::Metric;
 use crate::middleware::sign_check;
-use crate::types::{AppState, AppUtils, KVStorage, LogLevel};
+use crate::secret::Secret;
+use crate::types::{AppState, AppUtils, KVStorage};
 
-#[derive(Deserialize, Debug)]
+/// 序列化与 `Debug` 输出中的 `session_token` 已隐藏
+#[derive(Serialize, Deserialize, Debug)]
 pub struct WebhookPayload {
     pub meta: Meta,
     pub user: User,
     pub data: Value,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Meta {
     #[serde(rename = "type")]
     pub r#type: String,
     pub action: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     pub openid: String,
-    #[allow(dead_code)]
-    pub session_token: String,
+    pub session_token: Secret,
     pub nickname: String,
 }
 
 pub async fn webhook_handler<U: AppUtils, KV: KVStorage>(
     State(state): State<Arc<AppState<U, KV>>>,
-    Json(payload): Json<WebhookPayload>,
-) -> impl IntoResponse {
-    match (payload.meta.r#type.as_str(), payload.meta.action.as_str()) {
-        ("save", _) => {
-            save::handle_save(&payload, &state).await;
-        }
+    payload: Result<Json<WebhookPayload>, JsonRejection>,
+) -> Result<StatusCode, Error> {
+    let Json(payload) = payload.map_err(|e| Error::InvalidPayload(e.body_text()))?;
+    let received_at = state.utils.now();
 
-        ("user", "update" | "login" | "create") => {
-            user::handle_user_update_login_create(&payload, &state).await;
-        }
+    let span = tracing::info_span!(
+        "webhook",
+        openid = %payload.user.openid,
+        event_type = %payload.meta.r#type,
+        event_action = %payload.meta.action,
+    );
+
+    let result: Result<&str, Error> = async {
+        match (payload.meta.r#type.as_str(), payload.meta.action.as_str()) {
+            ("save", _) => {
+                save::handle_save(&payload, &state).await?;
+            }
+
+            ("user", "update" | "login" | "create") => {
+                user::handle_user_update_login_create(&payload, &state).await?;
+            }
 
-        (t, a) => {
-            state.utils.logger(
-                LogLevel::WARN,
-                &format!(
-                    "Unhandled webhook: type={}, action={}, payload={:?}",
-                    t, a, payload
-                ),
-            );
+            (t, a) => {
+                let msg = Msg::UnhandledWebhook {
+                    r#type: t,
+                    action: a,
+                };
+                tracing::warn!("{}", msg.text(state.utils.log_lang()));
+                return Ok("unhandled");
+            }
         }
+
+        tracing::info!("{}", Msg::WebhookHandled.text(state.utils.log_lang()));
+        Ok("ok")
+    }
+    .instrument(span)
+    .await;
+
+    state.utils.record(Metric::Webhook {
+        r#type: &payload.meta.r#type,
+        action: &payload.meta.action,
+        outcome: match &result {
+            Ok(outcome) => outcome,
+            Err(err) => err.code(),
+        },
+    });
+
+    let entry = AuditEntry {
+        received_at,
+        r#type: payload.meta.r#type.clone(),
+        action: payload.meta.action.clone(),
+        openid: payload.user.openid.clone(),
+        file_object_id: payload.data["file_object_id"].as_str().map(str::to_owned),
+        outcome: match &result {
+            Ok(outcome) => outcome.to_string(),
+            Err(err) => err.code().to_owned(),
+        },
+        message: result
+            .as_ref()
+            .err()
+            .map(|e| e.message(state.utils.log_lang())),
+        duration_ms: state.utils.now().saturating_sub(received_at),
+    };
+    // 审计记录写入失败不影响处理结果
+    if let Err(e) = audit::append(&state.kv, &payload.user.openid, entry).await {
+        let lang = state.utils.log_lang();
+        tracing::warn!("{}", Msg::AuditFailed(&e.message(lang)).text(lang));
     }
 
-    StatusCode::OK
+    result.map(|_| StatusCode::OK)
 }
 
 pub fn router<U: AppUtils, KV: KVStorage>(state: Arc<AppState<U, KV>>) -> Router {
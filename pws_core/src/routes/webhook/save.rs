@@ -2,35 +2,77 @@ use serde::Deserialize;
 use serde_json;
 use std::sync::Arc;
 
-use crate::types::{AppState, AppUtils, KVStorage, KVTable, LogLevel};
+use crate::error::Error;
+use crate::i18n::Msg;
+use crate::metrics::Metric;
+use crate::types::{AppState, AppUtils, KVStorage};
+use crate::{save, store};
 
 use super::WebhookPayload;
 
 #[derive(Deserialize, Debug)]
 struct Data {
     file_object_id: String,
-    #[allow(dead_code)]
     summary: String,
 }
 
 pub async fn handle_save<U: AppUtils, KV: KVStorage>(
     payload: &WebhookPayload,
     state: &Arc<AppState<U, KV>>,
-) {
-    let data: Data = match serde_json::from_value(payload.data.clone()) {
-        Ok(d) => d,
+) -> Result<(), Error> {
+    let data: Data = serde_json::from_value(payload.data.clone())
+        .map_err(|e| Error::InvalidPayload(e.to_string()))?;
+
+    let openid = &payload.user.openid;
+    let start = state.utils.now();
+    let file_data = state.utils.get_file(&data.file_object_id).await?;
+    let now = state.utils.now();
+    tracing::debug!(
+        file_object_id = %data.file_object_id,
+        bytes = file_data.len(),
+        duration_ms = now.saturating_sub(start),
+        "{}",
+        Msg::FileFetched.text(state.utils.log_lang())
+    );
+    // 摘要只是概览, 无法解码时不影响存档, 旧的摘要同样清除
+    let summary = match save::decode_summary(&data.summary) {
+        Ok(summary) => Some(summary),
+        Err(save::Error::EmptyField(_)) => None,
         Err(e) => {
             state
                 .utils
-                .logger(LogLevel::ERROR, &format!("Failed to parse data: {}", e));
-            return;
+                .record(Metric::ParseFailure { field: "summary" });
+            tracing::warn!(
+                "{}",
+                Msg::SummarySkipped(&e.message(state.utils.log_lang()))
+                    .text(state.utils.log_lang())
+            );
+            None
         }
     };
 
-    let openid = &payload.user.openid;
-    let save = state.kv.open_table("save").await;
-    let file_data = state.utils.get_file(&data.file_object_id);
-    let user = state.kv.open_table("user").await;
-    save.put(openid, &file_data.await).await;
-    user.put(openid, &payload.user.nickname.as_bytes()).await;
+    let saved = store::put_save(
+        &state.kv,
+        openid,
+        &data.file_object_id,
+        &file_data,
+        summary.as_ref(),
+        now,
+        state.utils.retention().keep_history,
+    )
+    .await?;
+    store::put_user(&state.kv, openid, &payload.user.nickname, now).await?;
+
+    // 存档照常保存, 旧的索引条目已被清除
+    if let Some(e) = saved.undecodable {
+        if let Some(field) = e.field() {
+            state.utils.record(Metric::ParseFailure { field });
+        }
+        tracing::warn!(
+            "{}",
+            Msg::RecordIndexSkipped(&e.message(state.utils.log_lang()))
+                .text(state.utils.log_lang())
+        );
+    }
+    Ok(())
 }
@@ -0,0 +1,225 @@
+//! Phigros 云存档解码
+//!
+//! 不依赖 axum, 关闭默认的 `http` feature 后可以单独使用:
+//!
+//! ```toml
+//! pws_core = { version = "0.1", default-features = false }
+//! ```
+//!
+//! ```no_run
+//! let bytes = std::fs::read("save.zip").unwrap();
+//! let save = pws_core::save::decode(&bytes).unwrap();
+//! println!("{}", save.user.avatar);
+//! ```
+//!
+//! 需要逐步处理时可以先 [`unzip`] 得到 [`Zip`], 再用 [`parse_save`] 或
+//! `decode_*` 系列函数解码单个条目。webhook 中随存档提交的摘要用 [`decode_summary`] 解码。
+
+use std::fmt;
+use std::io::{Cursor, Read, Seek};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bitvec::prelude::{BitSlice, Lsb0};
+use phi_save_codec::game_key::{field::GameKey, serde::SerializableGameKey};
+use phi_save_codec::game_progress::{field::GameProgress, serde::SerializableGameProgress};
+use phi_save_codec::game_record::{field::GameRecord, serde::SerializableGameRecord};
+use phi_save_codec::settings::{field::Settings, serde::SerializableSettings};
+use phi_save_codec::summary::{field::Summary, serde::SerializableSummary};
+use phi_save_codec::user::{field::User, serde::SerializableUser};
+use serde::{Deserialize, Serialize};
+use shua_struct::field::BinaryField;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::i18n::{Lang, Msg};
+use crate::utils::decrypt;
+
+pub use phi_save_codec;
+
+/// 压缩包内的条目名, 与 [`Zip`] 的字段一一对应
+pub const ENTRIES: &[&str] = &["gameKey", "gameProgress", "gameRecord", "user", "settings"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// 压缩包本身无法读取
+    Zip(String),
+    /// 缺少 [`ENTRIES`] 中的某个条目
+    MissingEntry(&'static str),
+    EmptyField(&'static str),
+    Decrypt {
+        field: &'static str,
+        message: String,
+    },
+    Parse {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Zip(_) | Error::MissingEntry(_) => "zip_error",
+            Error::Decrypt { .. } => "decrypt_error",
+            Error::Parse { .. } | Error::EmptyField(_) => "parse_error",
+        }
+    }
+
+    /// 出错的存档字段, 压缩包层面的错误为 `None`
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Error::Decrypt { field, .. }
+            | Error::Parse { field, .. }
+            | Error::EmptyField(field) => Some(field),
+            Error::Zip(_) | Error::MissingEntry(_) => None,
+        }
+    }
+
+    pub fn msg(&self) -> Msg<'_> {
+        match self {
+            Error::Zip(e) => Msg::ZipError(e),
+            Error::MissingEntry(entry) => Msg::MissingEntry(entry),
+            Error::EmptyField(field) => Msg::EmptyField(field),
+            Error::Decrypt { field, message } => Msg::DecryptError { field, message },
+            Error::Parse { field, message } => Msg::ParseError { field, message },
+        }
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        self.msg().text(lang)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Lang::En))
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// 解压后仍处于加密状态的各个条目
+#[derive(Debug, Default, Clone)]
+pub struct Zip {
+    pub game_key: Vec<u8>,
+    pub game_progress: Vec<u8>,
+    pub game_record: Vec<u8>,
+    pub user: Vec<u8>,
+    pub settings: Vec<u8>,
+}
+
+/// 解码后的完整存档
+#[derive(Serialize, Deserialize)]
+pub struct Save {
+    pub game_progress: SerializableGameProgress,
+    pub game_record: SerializableGameRecord,
+    pub user: SerializableUser,
+    pub game_key: SerializableGameKey,
+    pub settings: SerializableSettings,
+}
+
+/// 从压缩包中读出 [`ENTRIES`] 的全部条目, 不做解密
+pub fn unzip<R: Read + Seek>(reader: R) -> Result<Zip, Error> {
+    let mut archive = ZipArchive::new(reader).map_err(|e| Error::Zip(e.to_string()))?;
+
+    let mut zip = Zip::default();
+
+    for &name in ENTRIES {
+        let mut file = archive.by_name(name).map_err(|e| match e {
+            ZipError::FileNotFound => Error::MissingEntry(name),
+            e => Error::Zip(format!("{}: {}", name, e)),
+        })?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)
+            .map_err(|e| Error::Zip(format!("{}: {}", name, e)))?;
+
+        match name {
+            "gameKey" => zip.game_key = buf,
+            "gameProgress" => zip.game_progress = buf,
+            "gameRecord" => zip.game_record = buf,
+            "user" => zip.user = buf,
+            "settings" => zip.settings = buf,
+            _ => {}
+        }
+    }
+
+    Ok(zip)
+}
+
+/// 条目首字节为版本号, 其后为 AES-256-CBC 加密的数据
+fn decode_entry<T, S>(field: &'static str, raw: &[u8]) -> Result<S, Error>
+where
+    T: BinaryField<Lsb0>,
+    S: From<T>,
+{
+    let Some((_, encrypted)) = raw.split_first() else {
+        return Err(Error::EmptyField(field));
+    };
+    let decrypted = decrypt(encrypted).map_err(|message| Error::Decrypt { field, message })?;
+
+    let bits = BitSlice::<u8, Lsb0>::from_slice(&decrypted);
+    let (item, _) = T::parse(bits, &None).map_err(|message| Error::Parse { field, message })?;
+
+    Ok(S::from(item))
+}
+
+pub fn decode_game_key(raw: &[u8]) -> Result<SerializableGameKey, Error> {
+    decode_entry::<GameKey, _>("game_key", raw)
+}
+
+pub fn decode_game_progress(raw: &[u8]) -> Result<SerializableGameProgress, Error> {
+    decode_entry::<GameProgress, _>("game_progress", raw)
+}
+
+pub fn decode_game_record(raw: &[u8]) -> Result<SerializableGameRecord, Error> {
+    decode_entry::<GameRecord, _>("game_record", raw)
+}
+
+pub fn decode_user(raw: &[u8]) -> Result<SerializableUser, Error> {
+    decode_entry::<User, _>("user", raw)
+}
+
+pub fn decode_settings(raw: &[u8]) -> Result<SerializableSettings, Error> {
+    decode_entry::<Settings, _>("settings", raw)
+}
+
+/// 解码 TapTap 云存档的摘要: 存档版本, 课题模式等级, RKS, 游戏版本, 头像与各难度的完成数.
+/// 摘要为 base64 编码, 没有加密.
+pub fn decode_summary(raw: &str) -> Result<SerializableSummary, Error> {
+    const FIELD: &str = "summary";
+    if raw.is_empty() {
+        return Err(Error::EmptyField(FIELD));
+    }
+    let bytes = STANDARD.decode(raw).map_err(|e| Error::Parse {
+        field: FIELD,
+        message: e.to_string(),
+    })?;
+    let bits = BitSlice::<u8, Lsb0>::from_slice(&bytes);
+    let (summary, _) = Summary::parse(bits, &None).map_err(|message| Error::Parse {
+        field: FIELD,
+        message,
+    })?;
+    Ok(summary.into())
+}
+
+/// 解码全部条目, 遇到第一个失败的字段即返回
+pub fn parse_save(zip: Zip) -> Result<Save, Error> {
+    Ok(Save {
+        game_key: decode_game_key(&zip.game_key)?,
+        game_progress: decode_game_progress(&zip.game_progress)?,
+        game_record: decode_game_record(&zip.game_record)?,
+        user: decode_user(&zip.user)?,
+        settings: decode_settings(&zip.settings)?,
+    })
+}
+
+/// 从任意可定位的读取器解码, 例如 [`std::fs::File`]
+pub fn decode_reader<R: Read + Seek>(reader: R) -> Result<Save, Error> {
+    unzip(reader).and_then(parse_save)
+}
+
+/// 从内存中的压缩包解码
+pub fn decode(bytes: &[u8]) -> Result<Save, Error> {
+    decode_reader(Cursor::new(bytes))
+}
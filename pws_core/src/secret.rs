@@ -0,0 +1,48 @@
+//! 密钥与令牌的包装类型, 在日志, 调试输出与序列化结果中只显示 [`REDACTED`].
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+pub const REDACTED: &str = "[redacted]";
+
+/// 反序列化时透明读取原值; `Debug`, `Display` 与序列化都只输出 [`REDACTED`],
+/// 需要原值时调用 [`Secret::expose`].
+#[derive(Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret<T = String>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// 原值, 只应在真正使用密钥的地方调用
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
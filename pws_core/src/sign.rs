@@ -1,9 +1,15 @@
+//! webhook 的 `X-Sign` 签名: 以 `sign_key` 为密钥的 Blake2s-128 MAC, URL_SAFE base64 输出.
+//! 服务端与 Worker 的 [`crate::types::AppUtils::sign`] 都使用这里的实现.
+
 use base64::{Engine as _, engine::general_purpose::URL_SAFE};
 use blake2::{
     Blake2sMac,
     digest::{Mac, consts::U16},
 };
 
+/// Blake2s 密钥的最大长度, 超出时签名会 panic, 加载配置时应先检查
+pub const MAX_KEY_LEN: usize = 32;
+
 pub fn sign(key: &[u8], data: &[u8]) -> String {
     let mut mac =
         Blake2sMac::<U16>::new_with_salt_and_personal(key, &[], &[]).expect("invalid length");
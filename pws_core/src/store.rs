@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use blake2::{Blake2s256, Digest};
+use phi_save_codec::summary::serde::SerializableSummary;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+use crate::error::Error;
+use crate::types::{BlobInfo, BlobStore, KVStorage, KVTable, PutOptions};
+use crate::{audit, index, save};
+
+pub const USER_TABLE: &str = "user";
+pub const SAVE_TABLE: &str = "save";
+pub const SAVE_META_TABLE: &str = "save_meta";
+/// 随存档提交的摘要, 见 [`crate::save::decode_summary`]
+pub const SUMMARY_TABLE: &str = "summary";
+/// [`TableBlobStore`] 默认使用的表
+pub const BLOB_TABLE: &str = "blob";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserRecord {
+    pub nickname: String,
+    /// 旧版本只存了昵称, 没有更新时间
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+impl UserRecord {
+    /// 兼容旧版本直接以 UTF-8 存储昵称的数据
+    pub fn decode(raw: &[u8]) -> Self {
+        serde_json::from_slice(raw).unwrap_or_else(|_| UserRecord {
+            nickname: String::from_utf8_lossy(raw).into_owned(),
+            updated_at: None,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("UserRecord is always serializable")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveMeta {
+    pub file_object_id: String,
+    pub size: usize,
+    pub updated_at: u64,
+    /// 存档内容在 blob 存储中的哈希; 旧版本的存档直接存在 `save` 表中, 没有此字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// 被覆盖的旧存档, 由旧到新, 数量受 [`crate::retention::RetentionPolicy::keep_history`] 限制
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<SaveVersion>,
+}
+
+impl SaveMeta {
+    /// 只保留最新的 `keep` 个历史存档, 返回被删除的历史存档
+    pub fn trim_history(&mut self, keep: usize) -> Vec<SaveVersion> {
+        let excess = self.history.len().saturating_sub(keep);
+        self.history.drain(..excess).collect()
+    }
+
+    /// 当前存档与历史存档引用的 blob 及其大小
+    pub fn blobs(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.hash
+            .as_deref()
+            .map(|hash| (hash, self.size))
+            .into_iter()
+            .chain(self.history.iter().map(|v| (v.hash.as_str(), v.size)))
+    }
+}
+
+/// 一个历史存档, 内容仍在 blob 存储中
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveVersion {
+    pub file_object_id: String,
+    pub size: usize,
+    pub updated_at: u64,
+    pub hash: String,
+}
+
+/// 存档内容的 BLAKE2s-256 摘要, 小写十六进制
+pub fn blob_hash(data: &[u8]) -> String {
+    Blake2s256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(raw: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(raw).map_err(|e| Error::Storage(e.to_string()))
+}
+
+fn encode_json<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(value).map_err(|e| Error::Storage(e.to_string()))
+}
+
+pub async fn get_user<KV: KVStorage>(kv: &KV, openid: &str) -> Result<Option<UserRecord>, Error> {
+    let raw = kv.open_table(USER_TABLE).await?.get(openid).await?;
+    Ok(raw.as_deref().map(UserRecord::decode))
+}
+
+pub async fn put_user<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+    nickname: &str,
+    now: u64,
+) -> Result<(), Error> {
+    let record = UserRecord {
+        nickname: nickname.to_owned(),
+        updated_at: Some(now),
+    };
+    kv.open_table(USER_TABLE)
+        .await?
+        .put(openid, &record.encode())
+        .await
+}
+
+pub async fn list_users<KV: KVStorage>(kv: &KV) -> Result<Vec<(String, UserRecord)>, Error> {
+    let table = kv.open_table(USER_TABLE).await?;
+    let mut users = Vec::new();
+    for openid in table.list("").await? {
+        if let Some(raw) = table.get(&openid).await? {
+            users.push((openid, UserRecord::decode(&raw)));
+        }
+    }
+    Ok(users)
+}
+
+pub async fn get_save<KV: KVStorage>(kv: &KV, openid: &str) -> Result<Option<Vec<u8>>, Error> {
+    match get_save_meta(kv, openid).await? {
+        Some(SaveMeta {
+            hash: Some(hash), ..
+        }) => match kv.blobs().get(&hash).await? {
+            Some(data) => Ok(Some(data)),
+            None => Err(Error::Storage(format!("missing blob {}", hash))),
+        },
+        _ => kv.open_table(SAVE_TABLE).await?.get(openid).await,
+    }
+}
+
+pub async fn get_save_meta<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+) -> Result<Option<SaveMeta>, Error> {
+    match kv.open_table(SAVE_META_TABLE).await?.get(openid).await? {
+        Some(raw) => decode_json(&raw).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// [`put_save`] 的结果
+#[derive(Debug)]
+pub struct Saved {
+    pub meta: SaveMeta,
+    /// 存档无法解码时的错误, 此时存档照常保存, 该用户的成绩索引被清除
+    pub undecodable: Option<save::Error>,
+}
+
+/// 写入存档, 被覆盖的旧存档内容不同时记入历史, 历史最多保留 `keep_history` 个.
+/// 同时替换摘要 (为 `None` 时删除旧的摘要) 并按存档内容更新成绩索引,
+/// 所有写入存档的入口都经过这里, 以免索引与存档不一致.
+pub async fn put_save<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+    file_object_id: &str,
+    data: &[u8],
+    summary: Option<&SerializableSummary>,
+    now: u64,
+    keep_history: usize,
+) -> Result<Saved, Error> {
+    let hash = blob_hash(data);
+    kv.blobs().put(&hash, data).await?;
+
+    let mut history = Vec::new();
+    if let Some(previous) = get_save_meta(kv, openid).await? {
+        history = previous.history;
+        // 旧版本存在 `save` 表中的存档没有哈希, 不记入历史
+        if let Some(previous_hash) = previous.hash
+            && previous_hash != hash
+        {
+            history.push(SaveVersion {
+                file_object_id: previous.file_object_id,
+                size: previous.size,
+                updated_at: previous.updated_at,
+                hash: previous_hash,
+            });
+        }
+    }
+    let mut meta = SaveMeta {
+        file_object_id: file_object_id.to_owned(),
+        size: data.len(),
+        updated_at: now,
+        hash: Some(hash.clone()),
+        history,
+    };
+    meta.trim_history(keep_history);
+    put_save_meta(kv, openid, &meta).await?;
+    // 回收任务可能在写入 blob 之后, 写入元数据之前删除了它, 见 [`delete_stale_blobs`]
+    if kv.blobs().modified(&hash).await?.is_none() {
+        kv.blobs().put(&hash, data).await?;
+    }
+    // 旧版本直接存放在 `save` 表中的内容已被 blob 取代
+    kv.open_table(SAVE_TABLE).await?.delete(openid).await?;
+    put_summary(kv, openid, summary).await?;
+
+    let undecodable = match save::decode(data) {
+        Ok(save) => {
+            index::update(kv, openid, &save).await?;
+            None
+        }
+        Err(e) => {
+            index::remove(kv, openid).await?;
+            Some(e)
+        }
+    };
+    Ok(Saved { meta, undecodable })
+}
+
+pub async fn get_summary<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+) -> Result<Option<SerializableSummary>, Error> {
+    match kv.open_table(SUMMARY_TABLE).await?.get(openid).await? {
+        Some(raw) => decode_json(&raw).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// 写入存档摘要, 为 `None` 时删除旧的摘要
+pub async fn put_summary<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+    summary: Option<&SerializableSummary>,
+) -> Result<(), Error> {
+    let table = kv.open_table(SUMMARY_TABLE).await?;
+    match summary {
+        Some(summary) => table.put(openid, &encode_json(summary)?).await,
+        None => table.delete(openid).await,
+    }
+}
+
+/// 删除存档及其历史, 摘要与成绩索引, 保留用户记录. blob 由回收任务清理.
+pub async fn delete_save<KV: KVStorage>(kv: &KV, openid: &str) -> Result<(), Error> {
+    index::remove(kv, openid).await?;
+    for table in [SAVE_META_TABLE, SAVE_TABLE, SUMMARY_TABLE] {
+        kv.open_table(table).await?.delete(openid).await?;
+    }
+    Ok(())
+}
+
+pub async fn delete_user<KV: KVStorage>(kv: &KV, openid: &str) -> Result<(), Error> {
+    delete_save(kv, openid).await?;
+    audit::remove(kv, openid).await?;
+    kv.open_table(USER_TABLE).await?.delete(openid).await
+}
+
+/// 删除不再被任何存档引用的 blob, 返回被删除的哈希.
+/// 修改时间不早于 `before` 的 blob 可能刚写入而尚未登记, 会被跳过.
+pub async fn collect_blobs<KV: KVStorage>(kv: &KV, before: u64) -> Result<Vec<String>, Error> {
+    // 先列出 blob 再收集引用, 之后写入的 blob 不会被当作候选
+    let blobs = kv.blobs().list().await?;
+    let referenced = referenced_blobs(kv).await?;
+
+    let candidates = blobs
+        .into_iter()
+        .filter(|b| !referenced.contains(&b.hash) && b.modified.is_none_or(|m| m < before))
+        .map(|b| b.hash);
+    delete_stale_blobs(kv, candidates, before).await
+}
+
+/// [`delete_stale_blobs`] 每批删除的 blob 数, 限制暂存在内存中的内容大小
+const DELETE_BATCH: usize = 64;
+
+/// 删除一批候选 blob, 返回实际删除的哈希.
+///
+/// 删除与 [`put_save`] 不是原子的, 因此:
+/// - 删除前再读取一次修改时间, 收集引用期间相同内容再次写入时 [`BlobStore::put`] 会刷新它, 此时跳过;
+/// - 删除后再读取一次引用, 期间写入的存档元数据引用了已删除的 blob 时, 以删除前读出的内容写回.
+///
+/// 元数据在这次读取之后才写入的, 由 [`put_save`] 写入元数据后确认 blob 仍然存在.
+pub async fn delete_stale_blobs<KV: KVStorage>(
+    kv: &KV,
+    hashes: impl IntoIterator<Item = String>,
+    before: u64,
+) -> Result<Vec<String>, Error> {
+    let blobs = kv.blobs();
+    let mut hashes = hashes.into_iter().peekable();
+    let mut removed = Vec::new();
+
+    while hashes.peek().is_some() {
+        let mut deleted = Vec::new();
+        for hash in hashes.by_ref().take(DELETE_BATCH) {
+            if blobs.modified(&hash).await?.is_some_and(|m| m >= before) {
+                continue;
+            }
+            let Some(data) = blobs.get(&hash).await? else {
+                continue;
+            };
+            blobs.delete(&hash).await?;
+            deleted.push((hash, data));
+        }
+        if deleted.is_empty() {
+            continue;
+        }
+
+        let referenced = referenced_blobs(kv).await?;
+        for (hash, data) in deleted {
+            if referenced.contains(&hash) {
+                blobs.put(&hash, &data).await?;
+            } else {
+                removed.push(hash);
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// 存档元数据中引用的全部 blob 哈希, 包括历史存档
+pub async fn referenced_blobs<KV: KVStorage>(kv: &KV) -> Result<HashSet<String>, Error> {
+    let table = kv.open_table(SAVE_META_TABLE).await?;
+    let mut referenced = HashSet::new();
+    for openid in table.list("").await? {
+        if let Some(raw) = table.get(&openid).await? {
+            let meta: SaveMeta = decode_json(&raw)?;
+            referenced.extend(meta.hash);
+            referenced.extend(meta.history.into_iter().map(|v| v.hash));
+        }
+    }
+    Ok(referenced)
+}
+
+/// 覆盖写入存档元数据, 不改动存档内容
+pub async fn put_save_meta<KV: KVStorage>(
+    kv: &KV,
+    openid: &str,
+    meta: &SaveMeta,
+) -> Result<(), Error> {
+    kv.open_table(SAVE_META_TABLE)
+        .await?
+        .put(openid, &encode_json(meta)?)
+        .await
+}
+
+/// [`TableBlobStore`] 在值的元数据中记录修改时间所用的字段
+const MODIFIED_KEY: &str = "modified";
+
+/// 以任意 KV 表保存 blob, 键为哈希; 供没有文件系统的后端使用.
+/// 修改时间记在值的元数据中, 由 `now` 提供, 单位为 Unix 毫秒.
+pub struct TableBlobStore<T: KVTable> {
+    table: T,
+    now: fn() -> u64,
+}
+
+impl<T: KVTable> TableBlobStore<T> {
+    pub fn new(table: T, now: fn() -> u64) -> Self {
+        Self { table, now }
+    }
+}
+
+#[async_trait]
+impl<T: KVTable> BlobStore for TableBlobStore<T> {
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.table.get(hash).await
+    }
+
+    /// KV 只能连同值一起更新元数据, 因此内容已存在时同样整体重写
+    async fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        let mut metadata = Map::new();
+        metadata.insert(MODIFIED_KEY.to_owned(), (self.now)().into());
+        let options = PutOptions::default().with_metadata(metadata);
+        self.table.put_with_options(hash, data, &options).await
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), Error> {
+        self.table.delete(hash).await
+    }
+
+    async fn list(&self) -> Result<Vec<BlobInfo>, Error> {
+        Ok(self
+            .table
+            .list("")
+            .await?
+            .into_iter()
+            .map(|hash| BlobInfo {
+                hash,
+                modified: None,
+            })
+            .collect())
+    }
+
+    /// 旧版本写入的 blob 没有元数据, 视为无法得知
+    async fn modified(&self, hash: &str) -> Result<Option<u64>, Error> {
+        Ok(self
+            .table
+            .get_with_metadata(hash)
+            .await?
+            .and_then(|entry| entry.metadata?.get(MODIFIED_KEY)?.as_u64()))
+    }
+}
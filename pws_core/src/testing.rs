@@ -0,0 +1,494 @@
+//! 测试支持: 内存存储与可控的 [`AppUtils`], 不需要 redb 或 Cloudflare 即可驱动
+//! [`crate::routes::router`]. 需要启用 `test-support` feature.
+//!
+//! ```ignore
+//! let utils = FakeUtils::new(b"key").with_file("f1", encode_save(save));
+//! let kv = MemoryKVStorage::with_clock(utils.clock());
+//! let state = Arc::new(AppState { utils, kv });
+//! let _guard = state.utils.logs().capture();
+//! let resp = router(state.clone()).oneshot(request).await?;
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor, Write};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bitvec::prelude::Lsb0;
+use phi_save_codec::game_key::field::GameKey;
+use phi_save_codec::game_progress::field::GameProgress;
+use phi_save_codec::game_record::field::GameRecord;
+use phi_save_codec::settings::field::Settings;
+use phi_save_codec::summary::field::Summary;
+use phi_save_codec::summary::serde::SerializableSummary;
+use phi_save_codec::user::field::User;
+use shua_struct::field::BinaryField;
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::MakeWriter;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::error::Error;
+use crate::i18n::Lang;
+use crate::metrics::Metric;
+use crate::retention::RetentionPolicy;
+use crate::save::Save;
+use crate::secret::Secret;
+use crate::sign::sign;
+use crate::types::{AppUtils, BlobInfo, BlobStore, Entry, KVStorage, KVTable, Page, PutOptions};
+use crate::utils::encrypt;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+type Tables = Arc<Mutex<BTreeMap<String, BTreeMap<String, Vec<u8>>>>>;
+type Options = Arc<Mutex<HashMap<(String, String), PutOptions>>>;
+/// 哈希到内容与最后写入时间
+type Blobs = Arc<Mutex<BTreeMap<String, (Vec<u8>, u64)>>>;
+
+/// 可手动推进的时钟, Unix 毫秒, 从 0 开始. 克隆后共享同一个时间,
+/// 让 [`FakeUtils::now`] 与 [`MemoryKVStorage`] 的过期判断保持一致.
+#[derive(Clone, Default)]
+pub struct Clock(Arc<AtomicU64>);
+
+impl Clock {
+    pub fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// 时间前进 `ms` 毫秒
+    pub fn advance(&self, ms: u64) {
+        self.0.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+/// 全部数据保存在内存中的 [`KVStorage`], 克隆后共享同一份数据.
+/// 判断过期使用 [`Clock`], 与 [`FakeUtils`] 共用时用 [`MemoryKVStorage::with_clock`] 创建.
+#[derive(Clone)]
+pub struct MemoryKVStorage {
+    tables: Tables,
+    options: Options,
+    clock: Clock,
+    blobs: MemoryBlobStore,
+}
+
+impl MemoryKVStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_clock(clock: Clock) -> Self {
+        Self {
+            tables: Tables::default(),
+            options: Options::default(),
+            blobs: MemoryBlobStore {
+                blobs: Blobs::default(),
+                clock: clock.clone(),
+            },
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// 时间前进 `ms` 毫秒, 与共用同一 [`Clock`] 的 [`FakeUtils`] 一起前进
+    pub fn advance(&self, ms: u64) {
+        self.clock.advance(ms);
+    }
+
+    /// 某张表的全部内容, 表不存在时为空
+    pub fn dump(&self, table: &str) -> BTreeMap<String, Vec<u8>> {
+        lock(&self.tables).get(table).cloned().unwrap_or_default()
+    }
+}
+
+impl Default for MemoryKVStorage {
+    fn default() -> Self {
+        Self::with_clock(Clock::default())
+    }
+}
+
+#[async_trait]
+impl KVStorage for MemoryKVStorage {
+    type Table = MemoryKVTable;
+    type Blobs = MemoryBlobStore;
+
+    async fn open_table(&self, table: &str) -> Result<Self::Table, Error> {
+        lock(&self.tables).entry(table.to_owned()).or_default();
+        Ok(MemoryKVTable {
+            tables: self.tables.clone(),
+            options: self.options.clone(),
+            clock: self.clock.clone(),
+            name: table.to_owned(),
+        })
+    }
+
+    fn blobs(&self) -> &Self::Blobs {
+        &self.blobs
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryKVTable {
+    tables: Tables,
+    options: Options,
+    clock: Clock,
+    name: String,
+}
+
+impl MemoryKVTable {
+    fn with<R>(&self, f: impl FnOnce(&mut BTreeMap<String, Vec<u8>>) -> R) -> R {
+        f(lock(&self.tables).entry(self.name.clone()).or_default())
+    }
+
+    fn options(&self, key: &str) -> PutOptions {
+        lock(&self.options)
+            .get(&(self.name.clone(), key.to_owned()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn is_expired(&self, key: &str) -> bool {
+        self.options(key).is_expired(self.clock.now())
+    }
+}
+
+#[async_trait]
+impl KVTable for MemoryKVTable {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.get_with_metadata(key).await?.map(|entry| entry.value))
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.put_with_options(key, value, &PutOptions::default())
+            .await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.with(|t| t.remove(key));
+        lock(&self.options).remove(&(self.name.clone(), key.to_owned()));
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let keys: Vec<String> = self.with(|t| {
+            t.range(prefix.to_owned()..)
+                .map(|(key, _)| key)
+                .take_while(|key| key.starts_with(prefix))
+                .cloned()
+                .collect()
+        });
+        Ok(keys
+            .into_iter()
+            .filter(|key| !self.is_expired(key))
+            .collect())
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page, Error> {
+        let limit = limit.max(1);
+        let start = match cursor {
+            Some(after) if after >= prefix => Bound::Excluded(after.to_owned()),
+            _ => Bound::Included(prefix.to_owned()),
+        };
+        let keys: Vec<String> = self.with(|t| {
+            t.range((start, Bound::Unbounded))
+                .map(|(key, _)| key)
+                .take_while(|key| key.starts_with(prefix))
+                .filter(|key| !self.is_expired(key))
+                .take(limit.saturating_add(1))
+                .cloned()
+                .collect()
+        });
+        Ok(Page::from_lookahead(keys, limit))
+    }
+
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error> {
+        if self.is_expired(key) {
+            return Ok(None);
+        }
+        let value = self.with(|t| t.get(key).cloned());
+        Ok(value.map(|value| Entry::new(value, self.options(key))))
+    }
+
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error> {
+        self.with(|t| t.insert(key.to_owned(), value.to_vec()));
+        let id = (self.name.clone(), key.to_owned());
+        if *options == PutOptions::default() {
+            lock(&self.options).remove(&id);
+        } else {
+            lock(&self.options).insert(id, options.clone());
+        }
+        Ok(())
+    }
+}
+
+/// 内存中的 [`BlobStore`], 修改时间取自所属 [`MemoryKVStorage`] 的 [`Clock`]
+#[derive(Clone)]
+pub struct MemoryBlobStore {
+    blobs: Blobs,
+    clock: Clock,
+}
+
+#[async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(lock(&self.blobs).get(hash).map(|(data, _)| data.clone()))
+    }
+
+    async fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        let now = self.clock.now();
+        lock(&self.blobs)
+            .entry(hash.to_owned())
+            .and_modify(|(_, modified)| *modified = now)
+            .or_insert_with(|| (data.to_vec(), now));
+        Ok(())
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), Error> {
+        lock(&self.blobs).remove(hash);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<BlobInfo>, Error> {
+        Ok(lock(&self.blobs)
+            .iter()
+            .map(|(hash, (_, modified))| BlobInfo {
+                hash: hash.clone(),
+                modified: Some(*modified),
+            })
+            .collect())
+    }
+
+    async fn modified(&self, hash: &str) -> Result<Option<u64>, Error> {
+        Ok(lock(&self.blobs).get(hash).map(|(_, modified)| *modified))
+    }
+}
+
+/// 收集 `tracing` 输出的缓冲区, 克隆后共享同一份内容
+#[derive(Clone, Default)]
+pub struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    /// 在当前线程上把日志写入此缓冲区, 直到返回值被丢弃
+    pub fn capture(&self) -> DefaultGuard {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(self.clone())
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&lock(&self.0)).into_owned()
+    }
+}
+
+impl Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.0).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// 从内存表中提供存档文件的 [`AppUtils`], 记录拉取过的文件、上报的指标与日志.
+/// 时间取自 [`Clock`], 从 0 开始, 只在调用 [`FakeUtils::advance`] 时前进.
+pub struct FakeUtils {
+    files: Mutex<HashMap<String, Vec<u8>>>,
+    fetched: Mutex<Vec<String>>,
+    metrics: Mutex<Vec<String>>,
+    logs: Logs,
+    sign_key: Secret<Vec<u8>>,
+    admin_token: Option<Secret>,
+    retention: RetentionPolicy,
+    lang: Lang,
+    clock: Clock,
+    next_id: AtomicU64,
+}
+
+impl FakeUtils {
+    pub fn new(sign_key: &[u8]) -> Self {
+        Self {
+            files: Mutex::default(),
+            fetched: Mutex::default(),
+            metrics: Mutex::default(),
+            logs: Logs::default(),
+            sign_key: Secret::new(sign_key.to_vec()),
+            admin_token: None,
+            retention: RetentionPolicy::default(),
+            lang: Lang::En,
+            clock: Clock::default(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn with_file(self, file_obj_id: &str, data: Vec<u8>) -> Self {
+        self.insert_file(file_obj_id, data);
+        self
+    }
+
+    pub fn with_admin_token(mut self, token: &str) -> Self {
+        self.admin_token = Some(Secret::new(token.to_owned()));
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_log_lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// 与 `clock` 共用时间, 通常传入 [`MemoryKVStorage::clock`]
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    pub fn insert_file(&self, file_obj_id: &str, data: Vec<u8>) {
+        lock(&self.files).insert(file_obj_id.to_owned(), data);
+    }
+
+    /// 时间前进 `ms` 毫秒
+    pub fn advance(&self, ms: u64) {
+        self.clock.advance(ms);
+    }
+
+    /// 按调用顺序记录的 `get_file` 参数
+    pub fn fetched(&self) -> Vec<String> {
+        lock(&self.fetched).clone()
+    }
+
+    /// 以 `Debug` 格式记录的 [`Metric`]
+    pub fn metrics(&self) -> Vec<String> {
+        lock(&self.metrics).clone()
+    }
+
+    pub fn logs(&self) -> &Logs {
+        &self.logs
+    }
+}
+
+#[async_trait]
+impl AppUtils for FakeUtils {
+    async fn get_file(&self, file_obj_id: &str) -> Result<Vec<u8>, Error> {
+        lock(&self.fetched).push(file_obj_id.to_owned());
+        lock(&self.files)
+            .get(file_obj_id)
+            .cloned()
+            .ok_or_else(|| Error::Fetch(format!("no such file: {}", file_obj_id)))
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// 与服务端和 Worker 相同的 [`crate::sign::sign`]
+    fn sign(&self, data: &[u8]) -> String {
+        sign(self.sign_key.expose(), data)
+    }
+
+    fn request_id(&self) -> String {
+        format!("req-{}", self.next_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    fn log_lang(&self) -> Lang {
+        self.lang
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn record(&self, metric: Metric<'_>) {
+        lock(&self.metrics).push(format!("{:?}", metric));
+    }
+
+    fn admin_token(&self) -> Option<Secret> {
+        self.admin_token.clone()
+    }
+
+    fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
+}
+
+fn entry<T: BinaryField<Lsb0>>(value: T) -> Vec<u8> {
+    let bits = value.build(&None).expect("save field is always encodable");
+    // 首字节是条目格式版本, 解码时会跳过
+    let mut raw = vec![1];
+    raw.extend(encrypt(bits.as_raw_slice()));
+    raw
+}
+
+/// [`crate::save::decode_summary`] 的逆过程, 生成 webhook 中的 `summary`
+pub fn encode_summary(summary: SerializableSummary) -> String {
+    let bits = Summary::from(summary)
+        .build(&None)
+        .expect("summary is always encodable");
+    STANDARD.encode(bits.as_raw_slice())
+}
+
+/// [`crate::save::decode`] 的逆过程, 生成可被正常解码的存档压缩包
+pub fn encode_save(save: Save) -> Vec<u8> {
+    let entries = [
+        ("gameKey", entry(GameKey::from(save.game_key))),
+        (
+            "gameProgress",
+            entry(GameProgress::from(save.game_progress)),
+        ),
+        ("gameRecord", entry(GameRecord::from(save.game_record))),
+        ("user", entry(User::from(save.user))),
+        ("settings", entry(Settings::from(save.settings))),
+    ];
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        zip.start_file(name, SimpleFileOptions::default())
+            .and_then(|()| zip.write_all(&data).map_err(Into::into))
+            .expect("writing to memory never fails");
+    }
+    zip.finish()
+        .expect("writing to memory never fails")
+        .into_inner()
+}
@@ -1,20 +1,158 @@
 use async_trait::async_trait;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::level_filters::LevelFilter;
+
+use crate::audit::AUDIT_TABLE;
+use crate::error::Error;
+use crate::i18n::Lang;
+use crate::index::RECORD_INDEX_TABLE;
+use crate::metrics::Metric;
+use crate::retention::RetentionPolicy;
+use crate::secret::Secret;
+use crate::store::{SAVE_META_TABLE, SAVE_TABLE, SUMMARY_TABLE, USER_TABLE};
+
+/// 核心路由会用到的全部表, 各存储后端需保证它们可以打开
+pub const TABLES: &[&str] = &[
+    USER_TABLE,
+    SAVE_TABLE,
+    SAVE_META_TABLE,
+    RECORD_INDEX_TABLE,
+    SUMMARY_TABLE,
+    AUDIT_TABLE,
+];
 
 #[async_trait]
 pub trait KVStorage: Send + Sync + 'static {
     type Table: KVTable;
-    async fn open_table(&self, table: &str) -> Self::Table;
+    type Blobs: BlobStore;
+    async fn open_table(&self, table: &str) -> Result<Self::Table, Error>;
+    /// 存档文件等大对象的存储, KV 表中只保存其哈希
+    fn blobs(&self) -> &Self::Blobs;
+    /// 就绪检查, 确认存储当前可读写
+    async fn ready(&self) -> Result<(), Error>;
+}
+
+/// 写入时随值保存的选项, 后端按原样存储并在 [`KVTable::get_with_metadata`] 中返回
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PutOptions {
+    /// 小型 JSON 元数据, Cloudflare KV 限制序列化后不超过 1024 字节
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Map<String, Value>>,
+    /// 过期时间, Unix 毫秒; 过期后读取与列出时视为不存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl PutOptions {
+    pub fn with_metadata(mut self, metadata: Map<String, Value>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// 从 `now` (Unix 毫秒) 起 `ttl_secs` 秒后过期
+    pub fn with_ttl(mut self, now: u64, ttl_secs: u64) -> Self {
+        self.expires_at = Some(now.saturating_add(ttl_secs.saturating_mul(1000)));
+        self
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+/// [`KVTable::get_with_metadata`] 的结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub value: Vec<u8>,
+    pub metadata: Option<Map<String, Value>>,
+    pub expires_at: Option<u64>,
+}
+
+impl Entry {
+    pub fn new(value: Vec<u8>, options: PutOptions) -> Self {
+        Self {
+            value,
+            metadata: options.metadata,
+            expires_at: options.expires_at,
+        }
+    }
+}
+
+/// [`KVTable::list_page`] 的一页结果
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Page {
+    pub keys: Vec<String>,
+    /// 传给下一次调用以继续列出, 为 `None` 时已经列完
+    pub cursor: Option<String>,
+}
+
+impl Page {
+    /// 由按顺序多取一个的键生成一页: 超过 `limit` 个说明还有下一页, 游标为本页最后一个键
+    pub fn from_lookahead(mut keys: Vec<String>, limit: usize) -> Self {
+        let cursor = (keys.len() > limit).then(|| {
+            keys.truncate(limit);
+            keys[limit - 1].clone()
+        });
+        Self { keys, cursor }
+    }
 }
 
 #[async_trait]
 pub trait KVTable: Send + Sync {
-    async fn get(&self, key: &str) -> Option<Vec<u8>>;
-    async fn put(&self, key: &str, value: &[u8]);
-    async fn delete(&self, key: &str);
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+    /// 写入并清除该键原有的元数据与过期时间
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error>;
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+    /// 按字典序列出以 `prefix` 开头的全部键
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error>;
+    /// 分页列出以 `prefix` 开头的键, 每页最多 `limit` 个. `cursor` 为上一页返回的游标,
+    /// 其格式由后端决定. 页中的键可能少于 `limit`, 以返回的游标判断是否列完.
+    ///
+    /// 默认实现每页都调用一次 [`KVTable::list`], 后端应覆盖它以免载入全部键.
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page, Error> {
+        let limit = limit.max(1);
+        let mut keys = self.list(prefix).await?;
+        let start = cursor.map_or(0, |after| keys.partition_point(|k| k.as_str() <= after));
+        keys.truncate(start.saturating_add(limit).saturating_add(1));
+        Ok(Page::from_lookahead(keys.split_off(start), limit))
+    }
+    /// 读取值及写入时的 [`PutOptions`]
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error>;
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error>;
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+/// 按内容哈希寻址的大对象存储, 哈希由 [`crate::store::blob_hash`] 计算
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, Error>;
+    /// 内容已存在时不重复写入, 只刷新修改时间
+    async fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error>;
+    async fn delete(&self, hash: &str) -> Result<(), Error>;
+    async fn list(&self) -> Result<Vec<BlobInfo>, Error>;
+    /// 单个 blob 最后写入或刷新的时间, Unix 毫秒; 不存在或无法得知时为 `None`.
+    /// 回收前用它再次确认 blob 没有在列出之后被重新写入.
+    async fn modified(&self, hash: &str) -> Result<Option<u64>, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct BlobInfo {
+    pub hash: String,
+    /// 最后写入时间, Unix 毫秒; 无法得知时为 `None`
+    pub modified: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum LogLevel {
     DEBUG,
     INFO,
@@ -24,11 +162,37 @@ pub enum LogLevel {
     UNKNOWN,
 }
 
+impl LogLevel {
+    pub fn level_filter(self) -> LevelFilter {
+        match self {
+            LogLevel::DEBUG => LevelFilter::DEBUG,
+            LogLevel::INFO => LevelFilter::INFO,
+            LogLevel::WARN => LevelFilter::WARN,
+            LogLevel::ERROR => LevelFilter::ERROR,
+            LogLevel::UNKNOWN => LevelFilter::OFF,
+        }
+    }
+}
+
 #[async_trait]
 pub trait AppUtils: Send + Sync + 'static {
-    async fn get_file(&self, file_obj_id: &str) -> Vec<u8>;
+    async fn get_file(&self, file_obj_id: &str) -> Result<Vec<u8>, Error>;
+    /// 就绪检查, 确认存档文件的来源可达
+    async fn ready(&self) -> Result<(), Error>;
     fn sign(&self, data: &[u8]) -> String;
-    fn logger(&self, level: LogLevel, msg: &str);
+    fn request_id(&self) -> String;
+    fn log_lang(&self) -> Lang;
+    /// Unix 时间戳, 单位毫秒
+    fn now(&self) -> u64;
+    fn record(&self, _metric: Metric<'_>) {}
+    /// 管理接口的 Bearer token, 为 `None` 时禁用 `/admin`
+    fn admin_token(&self) -> Option<Secret> {
+        None
+    }
+    /// 数据保留策略, 写入存档时据此限制历史数量
+    fn retention(&self) -> RetentionPolicy {
+        RetentionPolicy::default()
+    }
 }
 
 pub struct AppState<U: AppUtils, KV: KVStorage> {
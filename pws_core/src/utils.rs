@@ -16,6 +16,7 @@ const AES_IV: &[u8; 16] = &[
     0x2a, 0x4f, 0xf0, 0x8a, 0xc8, 0x0d, 0x63, 0x07, 0x00, 0x57, 0xc5, 0x95, 0x18, 0xc8, 0x32, 0x53,
 ];
 
+#[cfg(feature = "http")]
 #[inline]
 pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
@@ -35,7 +36,7 @@ pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
 pub fn encrypt(data: &[u8]) -> Vec<u8> {
     let mut buf = data.to_vec();
     let pad_len = 16 - (buf.len() % 16);
-    buf.extend(std::iter::repeat(0u8).take(pad_len));
+    buf.extend(std::iter::repeat_n(0u8, pad_len));
 
     let ct = Aes256CbcEnc::new(AES_KEY.into(), AES_IV.into())
         .encrypt_padded_mut::<Pkcs7>(&mut buf, data.len())
@@ -49,7 +50,27 @@ pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, String> {
 
     let pt = Aes256CbcDec::new(AES_KEY.into(), AES_IV.into())
         .decrypt_padded_mut::<Pkcs7>(&mut buf)
-        .map_err(|e| format!("解密失败: {:?}", e))?;
+        .map_err(|e| format!("{:?}", e))?;
 
     Ok(pt.to_vec())
 }
+
+/// 拆分 `Accept` 与 `Accept-Language` 等请求头, 返回每一项及其 q 值.
+/// q 值只能取 0 到 1, 无法解析、NaN、负数或大于 1 的 q 值使该项被丢弃.
+pub fn quality_values(header: &str) -> impl Iterator<Item = (&str, f32)> {
+    header.split(',').filter_map(|range| {
+        let mut params = range.split(';');
+        let value = params.next().unwrap_or_default().trim();
+
+        let q = match params.find_map(|p| p.trim().strip_prefix("q=")) {
+            Some(q) => q
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|q| (0.0..=1.0).contains(q))?,
+            None => 1.0,
+        };
+
+        Some((value, q))
+    })
+}
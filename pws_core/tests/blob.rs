@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use pws_core::error::Error;
+use pws_core::store::{self, TableBlobStore};
+use pws_core::testing::{MemoryKVStorage, MemoryKVTable};
+use pws_core::types::{BlobInfo, BlobStore, KVStorage, KVTable};
+
+#[tokio::test]
+async fn collect_blobs_keeps_blobs_written_within_the_grace_period() {
+    let kv = MemoryKVStorage::new();
+    store::put_save(&kv, "open-1", "file", b"v1", None, 0, 0)
+        .await
+        .unwrap();
+    store::put_save(&kv, "open-1", "file", b"v2", None, 0, 0)
+        .await
+        .unwrap();
+    kv.advance(10_000);
+    // 已写入 blob 但元数据尚未登记的存档
+    let pending = store::blob_hash(b"pending");
+    kv.blobs().put(&pending, b"pending").await.unwrap();
+
+    let removed = store::collect_blobs(&kv, 5_000).await.unwrap();
+    assert_eq!(removed, [store::blob_hash(b"v1")]);
+    assert!(kv.blobs().get(&pending).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn rewritten_blob_is_not_deleted() {
+    let kv = MemoryKVStorage::new();
+    let hash = store::blob_hash(b"v1");
+    kv.blobs().put(&hash, b"v1").await.unwrap();
+
+    // 回收列出候选之后, 相同内容的存档再次写入
+    kv.advance(10_000);
+    kv.blobs().put(&hash, b"v1").await.unwrap();
+    let removed = store::delete_stale_blobs(&kv, [hash.clone()], 5_000)
+        .await
+        .unwrap();
+    assert!(removed.is_empty());
+    assert!(kv.blobs().get(&hash).await.unwrap().is_some());
+
+    let removed = store::delete_stale_blobs(&kv, [hash.clone()], 20_000)
+        .await
+        .unwrap();
+    assert_eq!(removed, [hash.as_str()]);
+    assert!(kv.blobs().get(&hash).await.unwrap().is_none());
+}
+
+/// 在 blob 的删除或写入中插入并发的存档写入, 模拟回收与 [`store::put_save`] 交错执行
+#[derive(Clone)]
+struct Racing {
+    kv: MemoryKVStorage,
+    blobs: RacingBlobs,
+}
+
+#[derive(Clone)]
+struct RacingBlobs {
+    kv: MemoryKVStorage,
+    /// 下一次删除前以这份内容写入 `open-2` 的存档
+    save_before_delete: Arc<Mutex<Option<Vec<u8>>>>,
+    /// 下一次写入后立即删除, 如同回收任务恰好在此时执行
+    delete_after_put: Arc<Mutex<bool>>,
+}
+
+impl Racing {
+    fn new() -> Self {
+        let kv = MemoryKVStorage::new();
+        let blobs = RacingBlobs {
+            kv: kv.clone(),
+            save_before_delete: Arc::default(),
+            delete_after_put: Arc::default(),
+        };
+        Self { kv, blobs }
+    }
+}
+
+#[async_trait]
+impl KVStorage for Racing {
+    type Table = MemoryKVTable;
+    type Blobs = RacingBlobs;
+
+    async fn open_table(&self, table: &str) -> Result<Self::Table, Error> {
+        self.kv.open_table(table).await
+    }
+
+    fn blobs(&self) -> &Self::Blobs {
+        &self.blobs
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BlobStore for RacingBlobs {
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.kv.blobs().get(hash).await
+    }
+
+    async fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        self.kv.blobs().put(hash, data).await?;
+        if std::mem::take(&mut *self.delete_after_put.lock().unwrap()) {
+            self.kv.blobs().delete(hash).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), Error> {
+        let save = self.save_before_delete.lock().unwrap().take();
+        if let Some(data) = save {
+            store::put_save(&self.kv, "open-2", "file-2", &data, None, 0, 0).await?;
+        }
+        self.kv.blobs().delete(hash).await
+    }
+
+    async fn list(&self) -> Result<Vec<BlobInfo>, Error> {
+        self.kv.blobs().list().await
+    }
+
+    async fn modified(&self, hash: &str) -> Result<Option<u64>, Error> {
+        self.kv.blobs().modified(hash).await
+    }
+}
+
+#[tokio::test]
+async fn blob_saved_during_collection_is_restored() {
+    let kv = Racing::new();
+    store::put_save(&kv, "open-1", "file", b"v1", None, 0, 0)
+        .await
+        .unwrap();
+    store::put_save(&kv, "open-1", "file", b"v2", None, 0, 0)
+        .await
+        .unwrap();
+    kv.kv.advance(10_000);
+
+    // 确认修改时间之后, 删除之前, 另一个用户上传了相同内容的存档
+    *kv.blobs.save_before_delete.lock().unwrap() = Some(b"v1".to_vec());
+    let removed = store::collect_blobs(&kv, 5_000).await.unwrap();
+    assert!(removed.is_empty());
+    assert_eq!(
+        store::get_save(&kv, "open-2").await.unwrap().as_deref(),
+        Some(&b"v1"[..])
+    );
+}
+
+#[tokio::test]
+async fn put_save_rewrites_a_blob_deleted_before_the_meta_is_written() {
+    let kv = Racing::new();
+    *kv.blobs.delete_after_put.lock().unwrap() = true;
+    store::put_save(&kv, "open-1", "file", b"v1", None, 0, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        store::get_save(&kv, "open-1").await.unwrap().as_deref(),
+        Some(&b"v1"[..])
+    );
+}
+
+static NOW: AtomicU64 = AtomicU64::new(1_000);
+
+fn now() -> u64 {
+    NOW.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+async fn table_blob_store_records_the_write_time() {
+    let kv = MemoryKVStorage::new();
+    let table = kv.open_table(store::BLOB_TABLE).await.unwrap();
+    let blobs = TableBlobStore::new(table.clone(), now);
+    let hash = store::blob_hash(b"zip");
+
+    assert_eq!(blobs.modified(&hash).await.unwrap(), None);
+    blobs.put(&hash, b"zip").await.unwrap();
+    assert_eq!(blobs.modified(&hash).await.unwrap(), Some(1_000));
+    NOW.store(2_000, Ordering::SeqCst);
+    blobs.put(&hash, b"zip").await.unwrap();
+    assert_eq!(blobs.modified(&hash).await.unwrap(), Some(2_000));
+    assert_eq!(
+        blobs.get(&hash).await.unwrap().as_deref(),
+        Some(&b"zip"[..])
+    );
+
+    // 旧版本直接写入表中的 blob 没有写入时间
+    let legacy = store::blob_hash(b"legacy");
+    table.put(&legacy, b"legacy").await.unwrap();
+    assert_eq!(blobs.modified(&legacy).await.unwrap(), None);
+}
@@ -0,0 +1,78 @@
+use pws_core::dump::{self, Error, Stats};
+use pws_core::store::{self, SAVE_META_TABLE, USER_TABLE};
+use pws_core::testing::MemoryKVStorage;
+
+async fn populated() -> MemoryKVStorage {
+    let kv = MemoryKVStorage::new();
+    store::put_user(&kv, "open-1", "Alice", 1).await.unwrap();
+    store::put_save(&kv, "open-1", "file-1", b"zip-1", None, 1, 0)
+        .await
+        .unwrap();
+    store::put_user(&kv, "open-2", "Bob", 2).await.unwrap();
+    kv
+}
+
+#[tokio::test]
+async fn export_and_copy_page_through_large_tables() {
+    let source = MemoryKVStorage::new();
+    for i in 0..2500 {
+        store::put_user(&source, &format!("open-{:04}", i), "Alice", 1)
+            .await
+            .unwrap();
+    }
+
+    let mut out = Vec::new();
+    let exported = dump::export(&source, &mut out).await.unwrap();
+    assert_eq!(exported.records, 2500);
+    let imported = MemoryKVStorage::new();
+    dump::import(&imported, out.as_slice()).await.unwrap();
+    assert_eq!(imported.dump(USER_TABLE), source.dump(USER_TABLE));
+
+    let copied = MemoryKVStorage::new();
+    assert_eq!(dump::copy(&source, &copied).await.unwrap().records, 2500);
+    assert_eq!(copied.dump(USER_TABLE), source.dump(USER_TABLE));
+}
+
+#[tokio::test]
+async fn export_then_import_restores_everything() {
+    let source = populated().await;
+    let mut out = Vec::new();
+    let exported = dump::export(&source, &mut out).await.unwrap();
+    assert_eq!(
+        exported,
+        Stats {
+            records: 3,
+            blobs: 1
+        }
+    );
+
+    let target = MemoryKVStorage::new();
+    let imported = dump::import(&target, out.as_slice()).await.unwrap();
+    assert_eq!(imported, exported);
+    for table in [USER_TABLE, SAVE_META_TABLE] {
+        assert_eq!(target.dump(table), source.dump(table));
+    }
+    assert_eq!(
+        store::get_save(&target, "open-1").await.unwrap().as_deref(),
+        Some(&b"zip-1"[..])
+    );
+}
+
+#[tokio::test]
+async fn import_rejects_truncated_or_tampered_dumps() {
+    let mut out = Vec::new();
+    dump::export(&populated().await, &mut out).await.unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    let truncated: String = text.lines().take(3).map(|l| format!("{}\n", l)).collect();
+    let result = dump::import(&MemoryKVStorage::new(), truncated.as_bytes()).await;
+    assert!(matches!(result, Err(Error::Truncated)));
+
+    let blob = text.lines().nth(1).unwrap();
+    let tampered = text.replace(blob, &blob.replace("\"value\":\"", "\"value\":\"AAAA"));
+    let result = dump::import(&MemoryKVStorage::new(), tampered.as_bytes()).await;
+    assert!(matches!(result, Err(Error::HashMismatch(_))));
+
+    let result = dump::import(&MemoryKVStorage::new(), &b"{\"a\":1}\n"[..]).await;
+    assert!(matches!(result, Err(Error::Unsupported(_))));
+}
@@ -0,0 +1,485 @@
+use std::sync::Arc;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use pws_core::audit::{self, AuditEntry};
+use pws_core::index;
+use pws_core::routes::router;
+use pws_core::save::{self, Save};
+use pws_core::store::{self, USER_TABLE};
+use pws_core::testing::{FakeUtils, MemoryKVStorage, encode_save, encode_summary};
+use pws_core::types::{AppState, AppUtils};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+const SIGN_KEY: &[u8] = b"test-sign-key";
+const SESSION_TOKEN: &str = "session-token-8f3a";
+
+fn fixture() -> Save {
+    serde_json::from_str(include_str!("fixtures/save.json")).unwrap()
+}
+
+fn state() -> Arc<AppState<FakeUtils, MemoryKVStorage>> {
+    let utils = FakeUtils::new(SIGN_KEY).with_file("file-1", encode_save(fixture()));
+    let kv = MemoryKVStorage::with_clock(utils.clock());
+    Arc::new(AppState { utils, kv })
+}
+
+fn save_webhook() -> Vec<u8> {
+    save_webhook_with_summary("")
+}
+
+fn save_webhook_with_summary(summary: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({
+        "meta": { "type": "save", "action": "create" },
+        "user": { "openid": "open-1", "nickname": "Alice", "session_token": SESSION_TOKEN },
+        "data": { "file_object_id": "file-1", "summary": summary }
+    }))
+    .unwrap()
+}
+
+fn summary() -> Value {
+    let level = |clear, fc, phi| json!({ "clear": clear, "fc": fc, "phi": phi });
+    json!({
+        "save_version": 6,
+        "challenge_mode_rank": 348,
+        "rks": 15.5,
+        "game_version": 112,
+        "avatar": "Glaciaxion",
+        "level": {
+            "ez": level(30, 28, 20),
+            "hd": level(25, 20, 10),
+            "in": level(12, 5, 1),
+            "at": level(2, 0, 0)
+        }
+    })
+}
+
+fn webhook(body: Vec<u8>, sign: Option<&str>) -> Request<Body> {
+    let mut req = Request::post("/webhook/tcs").header("content-type", "application/json");
+    if let Some(sign) = sign {
+        req = req.header("X-Sign", sign);
+    }
+    req.body(Body::from(body)).unwrap()
+}
+
+async fn send(state: &Arc<AppState<FakeUtils, MemoryKVStorage>>, req: Request<Body>) -> Response {
+    router(state.clone()).oneshot(req).await.unwrap()
+}
+
+async fn json_body(resp: Response) -> Value {
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[test]
+fn encoded_save_decodes_to_the_same_content() {
+    let decoded = save::decode(&encode_save(fixture())).unwrap();
+    assert_eq!(
+        serde_json::to_value(decoded).unwrap(),
+        serde_json::to_value(fixture()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn signed_webhook_is_served_as_curated() {
+    let state = state();
+    let _logs = state.utils.logs().capture();
+
+    let body = save_webhook();
+    let sign = state.utils.sign(&body);
+    let resp = send(&state, webhook(body, Some(&sign))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(state.utils.fetched(), ["file-1"]);
+    let ez = index::chart_records(&state.kv, "Glaciaxion.SunsetRay", "EZ")
+        .await
+        .unwrap();
+    assert!(ez[0].0 == "open-1" && ez[0].1.is_ap());
+
+    let req = Request::get("/info/open-1/curated")
+        .header("accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let resp = send(&state, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let curated = json_body(resp).await;
+    assert_eq!(curated["nickname"], "Alice");
+    assert_eq!(curated["device_name"], "Pixel 8");
+    assert_eq!(curated["money"]["kib"], 512);
+    assert_eq!(curated["user"]["avatar"], "Glaciaxion");
+    assert_eq!(
+        curated["record"]["Glaciaxion.SunsetRay"]["EZ"]["score"],
+        1_000_000
+    );
+    assert_eq!(
+        curated["record"]["Rrhar'il.TeamGrimoire"]["IN"]["acc"],
+        96.25
+    );
+
+    assert!(
+        state
+            .utils
+            .metrics()
+            .iter()
+            .any(|m| m.contains("Webhook") && m.contains("\"ok\""))
+    );
+    assert!(state.utils.logs().contents().contains("open-1"));
+}
+
+#[tokio::test]
+async fn summary_is_stored_and_served() {
+    let state = state();
+    let encoded = encode_summary(serde_json::from_value(summary()).unwrap());
+    let body = save_webhook_with_summary(&encoded);
+    let sign = state.utils.sign(&body);
+    assert_eq!(
+        send(&state, webhook(body, Some(&sign))).await.status(),
+        StatusCode::OK
+    );
+
+    let req = Request::get("/info/open-1/summary")
+        .header("accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let resp = send(&state, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    assert_eq!(body["nickname"], "Alice");
+    assert_eq!(body["summary"], summary());
+
+    // 摘要无法解码时存档照常保存, 旧的摘要被清除
+    let body = save_webhook_with_summary("not base64!");
+    let sign = state.utils.sign(&body);
+    assert_eq!(
+        send(&state, webhook(body, Some(&sign))).await.status(),
+        StatusCode::OK
+    );
+    assert!(
+        store::get_summary(&state.kv, "open-1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        store::get_save(&state.kv, "open-1")
+            .await
+            .unwrap()
+            .is_some()
+    );
+    let req = Request::get("/info/open-1/summary")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&state, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn webhooks_are_audited_without_leaking_secrets() {
+    let utils = FakeUtils::new(SIGN_KEY)
+        .with_file("file-1", encode_save(fixture()))
+        .with_admin_token("admin");
+    let kv = MemoryKVStorage::with_clock(utils.clock());
+    let state = Arc::new(AppState { utils, kv });
+    let _logs = state.utils.logs().capture();
+    let unhandled = serde_json::to_vec(&json!({
+        "meta": { "type": "game", "action": "unknown" },
+        "user": { "openid": "open-1", "nickname": "Alice", "session_token": SESSION_TOKEN },
+        "data": {}
+    }))
+    .unwrap();
+    for body in [save_webhook(), unhandled] {
+        let sign = state.utils.sign(&body);
+        assert_eq!(
+            send(&state, webhook(body, Some(&sign))).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let req = Request::get("/admin/users/open-1/webhooks")
+        .header("authorization", "Bearer admin")
+        .body(Body::empty())
+        .unwrap();
+    let resp = send(&state, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    assert!(!String::from_utf8_lossy(&bytes).contains(SESSION_TOKEN));
+    let entries: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+    assert_eq!(entries[0]["outcome"], "unhandled");
+    assert_eq!(entries[1]["outcome"], "ok");
+    assert_eq!(entries[1]["type"], "save");
+    assert_eq!(entries[1]["file_object_id"], "file-1");
+    assert_eq!(entries[1]["openid"], "open-1");
+    // 只记录事件类型等字段, 不保存请求体
+    assert!(
+        entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|e| e.get("payload").is_none())
+    );
+    // 未处理的 webhook 只记录事件类型, 不输出请求体
+    let logs = state.utils.logs().contents();
+    assert!(logs.contains("type=game, action=unknown"));
+    assert!(!logs.contains("Alice"));
+    assert!(!logs.contains(SESSION_TOKEN));
+
+    // 超出上限时丢弃最旧的记录
+    let entry: AuditEntry = serde_json::from_value(entries[0].clone()).unwrap();
+    for _ in 0..audit::MAX_ENTRIES {
+        audit::append(&state.kv, "open-1", entry.clone())
+            .await
+            .unwrap();
+    }
+    let kept = audit::entries(&state.kv, "open-1").await.unwrap();
+    assert_eq!(kept.len(), audit::MAX_ENTRIES);
+    assert!(kept.iter().all(|e| e.outcome == "unhandled"));
+}
+
+#[tokio::test]
+async fn admin_refetch_updates_the_index_and_keeps_the_summary() {
+    let mut updated = serde_json::to_value(fixture()).unwrap();
+    let records = updated["game_record"].as_object_mut().unwrap();
+    records["Glaciaxion.SunsetRay"]["EZ"]["score"] = json!(990_000);
+    records.remove("Rrhar'il.TeamGrimoire");
+    let utils = FakeUtils::new(SIGN_KEY)
+        .with_file("file-1", encode_save(fixture()))
+        .with_file(
+            "file-2",
+            encode_save(serde_json::from_value(updated).unwrap()),
+        )
+        .with_file("file-3", b"not a zip".to_vec())
+        .with_admin_token("admin");
+    let kv = MemoryKVStorage::with_clock(utils.clock());
+    let state = Arc::new(AppState { utils, kv });
+
+    let body =
+        save_webhook_with_summary(&encode_summary(serde_json::from_value(summary()).unwrap()));
+    let sign = state.utils.sign(&body);
+    assert_eq!(
+        send(&state, webhook(body, Some(&sign))).await.status(),
+        StatusCode::OK
+    );
+    let stored = store::get_summary(&state.kv, "open-1").await.unwrap();
+    assert!(stored.is_some());
+    let stored = serde_json::to_value(stored).unwrap();
+
+    let refetch = |file: &str| {
+        Request::post("/admin/users/open-1/refetch")
+            .header("authorization", "Bearer admin")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "file_object_id": file }).to_string()))
+            .unwrap()
+    };
+    let resp = send(&state, refetch("file-2")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["file_object_id"], "file-2");
+    let ez = index::chart_records(&state.kv, "Glaciaxion.SunsetRay", "EZ")
+        .await
+        .unwrap();
+    assert_eq!(ez.len(), 1);
+    assert_eq!(ez[0].1.score, 990_000);
+    assert!(
+        index::chart_records(&state.kv, "Rrhar'il.TeamGrimoire", "IN")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    // 重新拉取时沿用已有的摘要
+    let kept = store::get_summary(&state.kv, "open-1").await.unwrap();
+    assert_eq!(serde_json::to_value(kept).unwrap(), stored);
+    let resp = send(
+        &state,
+        Request::get("/info/open-1/summary")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // 无法解码的存档照常保存, 旧的索引条目被清除
+    assert_eq!(
+        send(&state, refetch("file-3")).await.status(),
+        StatusCode::OK
+    );
+    assert!(
+        index::chart_records(&state.kv, "Glaciaxion.SunsetRay", "EZ")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // 每次重新拉取都记入审计记录, 失败时记下错误码
+    assert_eq!(
+        send(&state, refetch("missing")).await.status(),
+        StatusCode::BAD_GATEWAY
+    );
+    let entries = audit::entries(&state.kv, "open-1").await.unwrap();
+    let refetched: Vec<_> = entries
+        .iter()
+        .filter(|e| e.r#type == "admin")
+        .map(|e| {
+            (
+                e.action.as_str(),
+                e.file_object_id.as_deref(),
+                e.outcome.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        refetched,
+        [
+            ("refetch", Some("file-2"), "ok"),
+            ("refetch", Some("file-3"), "ok"),
+            ("refetch", Some("missing"), "fetch_error"),
+        ]
+    );
+}
+
+#[test]
+fn fake_sign_matches_the_server_format() {
+    let utils = FakeUtils::new(SIGN_KEY);
+    let body = save_webhook();
+    let signed = utils.sign(&body);
+    assert_eq!(signed, pws_core::sign::sign(SIGN_KEY, &body));
+    // Blake2s-128 的 16 字节经 URL_SAFE base64 编码后为 24 个字符
+    assert_eq!(signed.len(), 24);
+    assert!(!signed.contains(['+', '/']));
+}
+
+#[tokio::test]
+async fn bad_sign_is_rejected() {
+    let state = state();
+    let body = save_webhook();
+
+    let resp = send(&state, webhook(body.clone(), Some("not-a-valid-sign"))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json_body(resp).await["code"], "invalid_sign");
+
+    // 签名必须覆盖完整的请求体
+    let mut tampered = body.clone();
+    tampered.extend_from_slice(b" ");
+    let sign = state.utils.sign(&body);
+    let resp = send(&state, webhook(tampered, Some(&sign))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = send(&state, webhook(body, None)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json_body(resp).await["code"], "missing_sign");
+
+    assert!(state.utils.fetched().is_empty());
+    assert!(state.kv.dump(USER_TABLE).is_empty());
+}
+
+async fn stored_state() -> Arc<AppState<FakeUtils, MemoryKVStorage>> {
+    let state = state();
+    let body = save_webhook();
+    let sign = state.utils.sign(&body);
+    assert_eq!(
+        send(&state, webhook(body, Some(&sign))).await.status(),
+        StatusCode::OK
+    );
+    state
+}
+
+async fn curated(
+    state: &Arc<AppState<FakeUtils, MemoryKVStorage>>,
+    accept: &str,
+) -> (StatusCode, Option<String>, Vec<u8>) {
+    let req = Request::get("/info/open-1/curated")
+        .header("accept", accept)
+        .body(Body::empty())
+        .unwrap();
+    let resp = send(state, req).await;
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .map(|v| v.to_str().unwrap().to_owned());
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, bytes.to_vec())
+}
+
+#[tokio::test]
+async fn info_is_encoded_as_cbor_and_msgpack() {
+    let state = stored_state().await;
+    let (_, _, json) = curated(&state, "application/json").await;
+    let expected: Value = serde_json::from_slice(&json).unwrap();
+
+    let (status, content_type, body) = curated(&state, "application/cbor").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("application/cbor"));
+    let decoded: Value = ciborium::from_reader(body.as_slice()).unwrap();
+    assert_eq!(decoded, expected);
+
+    let (status, content_type, body) = curated(&state, "application/x-msgpack").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("application/msgpack"));
+    let decoded: Value = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[tokio::test]
+async fn accept_is_negotiated_by_q_value() {
+    let state = stored_state().await;
+    for (accept, expected) in [
+        (
+            "application/json;q=0.5, application/cbor",
+            "application/cbor",
+        ),
+        (
+            "application/cbor;q=0.2, application/msgpack;q=0.8",
+            "application/msgpack",
+        ),
+        ("text/html, application/cbor;q=0.1", "application/cbor"),
+        ("application/cbor;q=0, */*;q=0.1", "application/json"),
+        // 通配符不会选中以 q=0 排除的格式
+        ("application/json;q=0, */*", "application/cbor"),
+        (
+            "*/*;q=0.5, application/json;q=0, application/cbor;q=0",
+            "application/msgpack",
+        ),
+        ("application/json;q=0, application/*", "application/cbor"),
+        // 超出范围或无法解析的 q 值使该项无效
+        (
+            "application/cbor;q=2, application/json;q=0.1",
+            "application/json",
+        ),
+        (
+            "application/cbor;q=NaN, application/json;q=0.1",
+            "application/json",
+        ),
+        (
+            "application/cbor;q=-1, application/json;q=0.1",
+            "application/json",
+        ),
+        (
+            "application/cbor;q=abc, application/json;q=0.1",
+            "application/json",
+        ),
+    ] {
+        let (status, content_type, _) = curated(&state, accept).await;
+        assert_eq!(status, StatusCode::OK, "{accept}");
+        assert_eq!(content_type.as_deref(), Some(expected), "{accept}");
+    }
+}
+
+#[tokio::test]
+async fn unsupported_accept_is_not_acceptable() {
+    let state = stored_state().await;
+    for accept in [
+        "text/html",
+        "application/cbor;q=0",
+        "application/json;q=0",
+        "application/json;q=0, application/cbor;q=0, application/msgpack;q=0, */*",
+        "application/cbor;q=NaN",
+        "application/json;q=-0.5",
+        "application/msgpack;q=1.5",
+    ] {
+        let (status, _, body) = curated(&state, accept).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE, "{accept}");
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "not_acceptable");
+    }
+}
@@ -0,0 +1,32 @@
+use pws_core::i18n::Lang;
+
+#[test]
+fn accept_language_is_negotiated_by_q_value() {
+    for (header, expected) in [
+        ("en-GB, zh;q=0.5", Some(Lang::En)),
+        ("zh-Hans-CN;q=0.3, en;q=0.8", Some(Lang::En)),
+        ("fr, en;q=0.1", Some(Lang::En)),
+        ("en;q=0, zh", Some(Lang::ZhCN)),
+        ("fr, de", None),
+        ("en;q=0", None),
+    ] {
+        assert_eq!(Lang::from_accept_language(header), expected, "{header}");
+    }
+}
+
+#[test]
+fn invalid_q_values_drop_the_range() {
+    for header in [
+        "en;q=abc, zh;q=0.1",
+        "en;q=2, zh;q=0.1",
+        "en;q=-1, zh;q=0.1",
+        "en;q=NaN, zh;q=0.1",
+    ] {
+        assert_eq!(
+            Lang::from_accept_language(header),
+            Some(Lang::ZhCN),
+            "{header}"
+        );
+    }
+    assert_eq!(Lang::from_accept_language("en;q=1.5"), None);
+}
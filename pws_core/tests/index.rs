@@ -0,0 +1,90 @@
+use pws_core::index::{self, ChartRecord, RECORD_INDEX_TABLE};
+use pws_core::save::Save;
+use pws_core::store;
+use pws_core::testing::{MemoryKVStorage, encode_save};
+use serde_json::Value;
+
+fn fixture(edit: impl FnOnce(&mut Value)) -> Save {
+    let mut value: Value = serde_json::from_str(include_str!("fixtures/save.json")).unwrap();
+    edit(&mut value);
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test]
+async fn update_replaces_stale_entries_and_delete_clears_them() {
+    let kv = MemoryKVStorage::new();
+    assert_eq!(
+        index::update(&kv, "open-1", &fixture(|_| {}))
+            .await
+            .unwrap(),
+        3
+    );
+    assert_eq!(
+        index::chart_records(&kv, "Glaciaxion.SunsetRay", "EZ")
+            .await
+            .unwrap(),
+        [(
+            "open-1".to_owned(),
+            ChartRecord {
+                score: 1_000_000,
+                acc: 100.0,
+                fc: true
+            }
+        )]
+    );
+
+    let without_rrharil = fixture(|v| {
+        v["game_record"]
+            .as_object_mut()
+            .unwrap()
+            .remove("Rrhar'il.TeamGrimoire");
+    });
+    assert_eq!(
+        index::update(&kv, "open-1", &without_rrharil)
+            .await
+            .unwrap(),
+        2
+    );
+    assert!(
+        index::chart_records(&kv, "Rrhar'il.TeamGrimoire", "IN")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    // 每个玩家只有一条索引
+    assert_eq!(kv.dump(RECORD_INDEX_TABLE).len(), 1);
+
+    store::delete_user(&kv, "open-1").await.unwrap();
+    assert!(kv.dump(RECORD_INDEX_TABLE).is_empty());
+}
+
+#[tokio::test]
+async fn rebuild_regenerates_from_stored_saves() {
+    let kv = MemoryKVStorage::new();
+    let save = encode_save(fixture(|_| {}));
+    store::put_save(&kv, "open-1", "file-1", &save, None, 1, 0)
+        .await
+        .unwrap();
+    store::put_save(&kv, "open-2", "file-2", &save, None, 1, 0)
+        .await
+        .unwrap();
+    store::put_save(&kv, "open-3", "file-3", b"not a zip", None, 1, 0)
+        .await
+        .unwrap();
+    // 已不存在存档的残留条目会被清除
+    index::update(&kv, "gone", &fixture(|_| {})).await.unwrap();
+
+    let rebuilt = index::rebuild(&kv).await.unwrap();
+    assert_eq!((rebuilt.users, rebuilt.records), (2, 6));
+    assert_eq!(rebuilt.skipped.len(), 1);
+    assert_eq!(rebuilt.skipped[0].0, "open-3");
+
+    let players: Vec<String> = index::chart_records(&kv, "Glaciaxion.SunsetRay", "HD")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(openid, _)| openid)
+        .collect();
+    assert_eq!(players, ["open-1", "open-2"]);
+    assert_eq!(kv.dump(RECORD_INDEX_TABLE).len(), 2);
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use pws_core::dump;
+use pws_core::error::Error;
+use pws_core::testing::{FakeUtils, MemoryKVStorage, MemoryKVTable};
+use pws_core::types::{AppUtils, Entry, KVStorage, KVTable, Page, PutOptions};
+use serde_json::{Map, json};
+
+fn metadata() -> Map<String, serde_json::Value> {
+    json!({ "source": "webhook", "size": 3 })
+        .as_object()
+        .cloned()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn metadata_round_trips_and_plain_put_clears_it() {
+    let kv = MemoryKVStorage::new();
+    let table = kv.open_table("user").await.unwrap();
+    let options = PutOptions::default().with_metadata(metadata());
+    table.put_with_options("a", b"v1", &options).await.unwrap();
+
+    let entry = table.get_with_metadata("a").await.unwrap().unwrap();
+    assert_eq!(entry.value, b"v1");
+    assert_eq!(entry.metadata, Some(metadata()));
+    assert_eq!(entry.expires_at, None);
+
+    // 导出再导入后元数据保留
+    let mut out = Vec::new();
+    dump::export(&kv, &mut out).await.unwrap();
+    let restored = MemoryKVStorage::new();
+    dump::import(&restored, out.as_slice()).await.unwrap();
+    let entry = restored
+        .open_table("user")
+        .await
+        .unwrap()
+        .get_with_metadata("a")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.metadata, Some(metadata()));
+
+    table.put("a", b"v2").await.unwrap();
+    let entry = table.get_with_metadata("a").await.unwrap().unwrap();
+    assert_eq!((entry.value.as_slice(), entry.metadata), (&b"v2"[..], None));
+}
+
+#[tokio::test]
+async fn expired_values_are_hidden_from_get_and_list() {
+    let kv = MemoryKVStorage::new();
+    let table = kv.open_table("user").await.unwrap();
+    let options = PutOptions::default().with_ttl(0, 60);
+    table.put_with_options("a", b"v", &options).await.unwrap();
+    table.put("b", b"v").await.unwrap();
+
+    kv.advance(59_999);
+    assert_eq!(
+        table
+            .get_with_metadata("a")
+            .await
+            .unwrap()
+            .unwrap()
+            .expires_at,
+        Some(60_000)
+    );
+    assert_eq!(table.list("").await.unwrap(), ["a", "b"]);
+
+    kv.advance(1);
+    assert!(table.get("a").await.unwrap().is_none());
+    assert_eq!(table.list("").await.unwrap(), ["b"]);
+}
+
+#[tokio::test]
+async fn fake_utils_and_memory_storage_share_a_clock() {
+    let utils = FakeUtils::new(b"key");
+    let kv = MemoryKVStorage::with_clock(utils.clock());
+    let table = kv.open_table("user").await.unwrap();
+    let options = PutOptions::default().with_ttl(utils.now(), 60);
+    table.put_with_options("a", b"v", &options).await.unwrap();
+
+    utils.advance(60_000);
+    assert_eq!(kv.clock().now(), 60_000);
+    assert!(table.get("a").await.unwrap().is_none());
+    kv.advance(1);
+    assert_eq!(utils.now(), 60_001);
+}
+
+/// 只实现必需方法的表, 用于检查 [`KVTable::list_page`] 的默认实现
+struct ListOnly(MemoryKVTable);
+
+#[async_trait]
+impl KVTable for ListOnly {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.0.get(key).await
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.0.put(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.0.delete(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        self.0.list(prefix).await
+    }
+
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error> {
+        self.0.get_with_metadata(key).await
+    }
+
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error> {
+        self.0.put_with_options(key, value, options).await
+    }
+}
+
+#[tokio::test]
+async fn default_list_page_follows_the_cursor() {
+    let kv = MemoryKVStorage::new();
+    let table = ListOnly(kv.open_table("user").await.unwrap());
+    for key in ["a/1", "a/2", "a/3", "b"] {
+        table.put(key, b"v").await.unwrap();
+    }
+
+    let first = table.list_page("a/", None, 2).await.unwrap();
+    assert_eq!(first.keys, ["a/1", "a/2"]);
+    let second = table
+        .list_page("a/", first.cursor.as_deref(), 2)
+        .await
+        .unwrap();
+    assert_eq!(
+        second,
+        Page {
+            keys: vec!["a/3".to_owned()],
+            cursor: None
+        }
+    );
+    // 与后端自己的实现结果相同
+    assert_eq!(table.0.list_page("a/", None, 2).await.unwrap(), first);
+}
@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use pws_core::routes::router;
+use pws_core::save::Save;
+use pws_core::testing::{FakeUtils, MemoryKVStorage, encode_save, encode_summary};
+use pws_core::types::{AppState, AppUtils};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+const SIGN_KEY: &[u8] = b"test-sign-key";
+const ADMIN_TOKEN: &str = "admin";
+
+fn summary() -> Value {
+    let level = |clear, fc, phi| json!({ "clear": clear, "fc": fc, "phi": phi });
+    json!({
+        "save_version": 6,
+        "challenge_mode_rank": 348,
+        "rks": 15.5,
+        "game_version": 112,
+        "avatar": "Glaciaxion",
+        "level": {
+            "ez": level(30, 28, 20),
+            "hd": level(25, 20, 10),
+            "in": level(12, 5, 1),
+            "at": level(2, 0, 0)
+        }
+    })
+}
+
+async fn state() -> Arc<AppState<FakeUtils, MemoryKVStorage>> {
+    let save: Save = serde_json::from_str(include_str!("fixtures/save.json")).unwrap();
+    let utils = FakeUtils::new(SIGN_KEY)
+        .with_file("file-1", encode_save(save))
+        .with_admin_token(ADMIN_TOKEN);
+    let kv = MemoryKVStorage::with_clock(utils.clock());
+    let state = Arc::new(AppState { utils, kv });
+    let body = serde_json::to_vec(&json!({
+        "meta": { "type": "save", "action": "create" },
+        "user": { "openid": "open-1", "nickname": "Alice", "session_token": "token" },
+        "data": {
+            "file_object_id": "file-1",
+            "summary": encode_summary(serde_json::from_value(summary()).unwrap())
+        }
+    }))
+    .unwrap();
+    let sign = state.utils.sign(&body);
+    let (status, _) = request(
+        &state,
+        Request::post("/webhook/tcs")
+            .header("content-type", "application/json")
+            .header("X-Sign", sign)
+            .body(Body::from(body))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    state
+}
+
+async fn request(
+    state: &Arc<AppState<FakeUtils, MemoryKVStorage>>,
+    req: Request<Body>,
+) -> (StatusCode, Value) {
+    let resp = router(state.clone()).oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+    (status, body)
+}
+
+fn resolve<'a>(doc: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(reference) => {
+            let pointer = reference.strip_prefix('#').unwrap();
+            resolve(doc, doc.pointer(pointer).unwrap())
+        }
+        None => schema,
+    }
+}
+
+fn type_matches(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_u64() || value.is_i64(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        other => panic!("unsupported schema type {other}"),
+    }
+}
+
+/// 只覆盖文档里用到的关键字, 对象上未声明的字段同样视为不符
+fn validate(doc: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = resolve(doc, schema);
+
+    if let Some(variants) = schema["oneOf"].as_array() {
+        let matched = variants
+            .iter()
+            .filter(|variant| {
+                let mut errs = Vec::new();
+                validate(doc, variant, value, path, &mut errs);
+                errs.is_empty()
+            })
+            .count();
+        if matched != 1 {
+            errors.push(format!("{path}: matches {matched} of oneOf"));
+        }
+        return;
+    }
+
+    match &schema["type"] {
+        Value::String(ty) if !type_matches(ty, value) => {
+            errors.push(format!("{path}: expected {ty}, got {value}"));
+            return;
+        }
+        Value::Array(types)
+            if !types
+                .iter()
+                .any(|ty| type_matches(ty.as_str().unwrap(), value)) =>
+        {
+            errors.push(format!("{path}: expected one of {types:?}, got {value}"));
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(n) = value.as_f64() {
+        if schema["minimum"].as_f64().is_some_and(|min| n < min) {
+            errors.push(format!("{path}: {n} below minimum"));
+        }
+        if schema["maximum"].as_f64().is_some_and(|max| n > max) {
+            errors.push(format!("{path}: {n} above maximum"));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if schema["minItems"]
+            .as_u64()
+            .is_some_and(|min| (items.len() as u64) < min)
+        {
+            errors.push(format!("{path}: fewer than minItems"));
+        }
+        if schema["maxItems"]
+            .as_u64()
+            .is_some_and(|max| (items.len() as u64) > max)
+        {
+            errors.push(format!("{path}: more than maxItems"));
+        }
+        if let Some(item) = schema.get("items") {
+            for (i, value) in items.iter().enumerate() {
+                validate(doc, item, value, &format!("{path}[{i}]"), errors);
+            }
+        }
+    }
+
+    if let Some(fields) = value.as_object() {
+        let properties = schema["properties"].as_object();
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let required = required.as_str().unwrap();
+            if !fields.contains_key(required) {
+                errors.push(format!("{path}: missing required `{required}`"));
+            }
+        }
+        for (name, value) in fields {
+            let path = format!("{path}.{name}");
+            if let Some(names) = schema["propertyNames"]["enum"].as_array()
+                && !names.iter().any(|n| n == name)
+            {
+                errors.push(format!("{path}: property name not in enum"));
+            }
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => validate(doc, property, value, &path, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(additional) => validate(doc, additional, value, &path, errors),
+                    None if properties.is_some() => {
+                        errors.push(format!("{path}: not declared in the schema"))
+                    }
+                    None => {}
+                },
+            }
+        }
+    }
+}
+
+fn assert_conforms(doc: &Value, schema: &Value, value: &Value, what: &str) {
+    let mut errors = Vec::new();
+    validate(doc, schema, value, "$", &mut errors);
+    assert!(errors.is_empty(), "{what}:\n{}", errors.join("\n"));
+}
+
+fn response_schema<'a>(doc: &'a Value, path: &str, method: &str, status: &str) -> &'a Value {
+    let content = &doc["paths"][path][method]["responses"][status]["content"];
+    let schema = &content["application/json"]["schema"];
+    assert!(!schema.is_null(), "{method} {path} {status} has no schema");
+    schema
+}
+
+#[tokio::test]
+async fn info_responses_match_the_served_schema() {
+    let state = state().await;
+    let (status, doc) = request(
+        &state,
+        Request::get("/openapi.json").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for route in ["all", "curated", "summary"] {
+        let uri = format!("/info/open-1/{route}");
+        let (status, body) = request(&state, Request::get(&uri).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        let schema = response_schema(&doc, &format!("/info/{{open_id}}/{route}"), "get", "200");
+        assert_conforms(&doc, schema, &body, &uri);
+    }
+
+    let uri = "/info/missing/curated";
+    let (status, body) = request(&state, Request::get(uri).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let schema = response_schema(&doc, "/info/{open_id}/curated", "get", "404");
+    assert_conforms(&doc, schema, &body, uri);
+}
+
+#[tokio::test]
+async fn admin_and_meta_responses_match_the_served_schema() {
+    let state = state().await;
+    let (_, doc) = request(
+        &state,
+        Request::get("/openapi.json").body(Body::empty()).unwrap(),
+    )
+    .await;
+    let admin = |method: &str, uri: &str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {ADMIN_TOKEN}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    for (method, uri, path) in [
+        ("get", "/admin/users", "/admin/users"),
+        ("get", "/admin/users/open-1", "/admin/users/{open_id}"),
+        (
+            "post",
+            "/admin/users/open-1/check",
+            "/admin/users/{open_id}/check",
+        ),
+        (
+            "get",
+            "/admin/users/open-1/webhooks",
+            "/admin/users/{open_id}/webhooks",
+        ),
+    ] {
+        let (status, body) = request(&state, admin(&method.to_uppercase(), uri)).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        assert_conforms(&doc, response_schema(&doc, path, method, "200"), &body, uri);
+    }
+
+    let (status, body) =
+        request(&state, Request::get("/readyz").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_conforms(
+        &doc,
+        response_schema(&doc, "/readyz", "get", "200"),
+        &body,
+        "/readyz",
+    );
+}
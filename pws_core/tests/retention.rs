@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use pws_core::retention::{self, RetentionPolicy};
+use pws_core::store::{self, SAVE_META_TABLE};
+use pws_core::testing::MemoryKVStorage;
+use pws_core::types::{BlobStore, KVStorage};
+
+const DAY_MS: u64 = 24 * 3600 * 1000;
+
+async fn blobs(kv: &MemoryKVStorage) -> HashSet<String> {
+    kv.blobs()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|b| b.hash)
+        .collect()
+}
+
+#[tokio::test]
+async fn put_save_keeps_the_latest_history_and_gc_preserves_it() {
+    let kv = MemoryKVStorage::new();
+    for (i, data) in [b"v1", b"v2", b"v2", b"v3", b"v4"].iter().enumerate() {
+        store::put_save(&kv, "open-1", "file", *data, None, i as u64, 2)
+            .await
+            .unwrap();
+    }
+
+    let meta = store::get_save_meta(&kv, "open-1").await.unwrap().unwrap();
+    // 内容未变的写入不产生历史
+    let history: Vec<u64> = meta.history.iter().map(|v| v.updated_at).collect();
+    assert_eq!(history, [2, 3]);
+    assert_eq!(meta.history[0].hash, store::blob_hash(b"v2"));
+
+    let removed = store::collect_blobs(&kv, u64::MAX).await.unwrap();
+    assert_eq!(removed, [store::blob_hash(b"v1")]);
+}
+
+#[tokio::test]
+async fn enforce_purges_expired_saves_and_trims_history() {
+    let kv = MemoryKVStorage::new();
+    let now = 100 * DAY_MS;
+    store::put_save(&kv, "stale", "file", b"old", None, DAY_MS, 0)
+        .await
+        .unwrap();
+    for (i, data) in [b"a", b"b", b"c"].iter().enumerate() {
+        store::put_save(&kv, "active", "file", *data, None, now - i as u64, 5)
+            .await
+            .unwrap();
+    }
+    store::put_user(&kv, "stale", "Alice", DAY_MS)
+        .await
+        .unwrap();
+
+    let policy = RetentionPolicy {
+        max_age_days: Some(30),
+        keep_history: 1,
+    };
+    let report = retention::enforce(&kv, &policy, now, true).await.unwrap();
+    assert_eq!(report.expired.len(), 1);
+    assert_eq!(report.expired[0].openid, "stale");
+    assert_eq!(report.trimmed, 1);
+    // 过期存档 "old" 与被裁剪的历史 "a" 不再被引用
+    assert_eq!((report.freed_blobs, report.freed_bytes), (2, 4));
+    assert_eq!(kv.dump(SAVE_META_TABLE).len(), 2);
+    assert_eq!(blobs(&kv).await.len(), 4);
+
+    let report = retention::enforce(&kv, &policy, now, false).await.unwrap();
+    assert_eq!((report.expired.len(), report.trimmed), (1, 1));
+    assert_eq!((report.freed_blobs, report.freed_bytes), (2, 4));
+    assert_eq!(
+        blobs(&kv).await,
+        [store::blob_hash(b"b"), store::blob_hash(b"c")]
+            .into_iter()
+            .collect()
+    );
+    assert!(store::get_save(&kv, "stale").await.unwrap().is_none());
+    // 只删除存档, 用户记录保留
+    assert!(store::get_user(&kv, "stale").await.unwrap().is_some());
+    let meta = store::get_save_meta(&kv, "active").await.unwrap().unwrap();
+    assert_eq!(meta.history.len(), 1);
+
+    let report = retention::enforce(&kv, &policy, now, false).await.unwrap();
+    assert_eq!((report.expired.len(), report.trimmed), (0, 0));
+    assert_eq!(report.freed_blobs, 0);
+}
+
+#[tokio::test]
+async fn enforce_keeps_blobs_still_referenced_elsewhere() {
+    let kv = MemoryKVStorage::new();
+    let now = 100 * DAY_MS;
+    store::put_save(&kv, "stale", "file", b"same", None, DAY_MS, 0)
+        .await
+        .unwrap();
+    store::put_save(&kv, "active", "file", b"same", None, now, 0)
+        .await
+        .unwrap();
+
+    let policy = RetentionPolicy {
+        max_age_days: Some(30),
+        keep_history: 0,
+    };
+    let report = retention::enforce(&kv, &policy, now, false).await.unwrap();
+    assert_eq!(report.expired.len(), 1);
+    assert_eq!(report.freed_blobs, 0);
+    assert_eq!(
+        store::get_save(&kv, "active").await.unwrap().as_deref(),
+        Some(&b"same"[..])
+    );
+}
+
+#[tokio::test]
+async fn enforce_skips_blobs_rewritten_after_it_started() {
+    let kv = MemoryKVStorage::new();
+    let now = 100 * DAY_MS;
+    store::put_save(&kv, "stale", "file", b"old", None, DAY_MS, 0)
+        .await
+        .unwrap();
+    // 相同内容在清理开始后再次写入, 修改时间晚于 `now`
+    kv.advance(now + 1);
+    kv.blobs()
+        .put(&store::blob_hash(b"old"), b"old")
+        .await
+        .unwrap();
+
+    let policy = RetentionPolicy {
+        max_age_days: Some(30),
+        keep_history: 0,
+    };
+    let report = retention::enforce(&kv, &policy, now, false).await.unwrap();
+    assert_eq!(report.expired.len(), 1);
+    assert_eq!(report.freed_blobs, 0);
+    assert_eq!(blobs(&kv).await.len(), 1);
+}
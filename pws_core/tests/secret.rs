@@ -0,0 +1,34 @@
+use pws_core::secret::{REDACTED, Secret};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Config {
+    name: String,
+    sign_key: Secret,
+    admin_token: Option<Secret>,
+}
+
+#[test]
+fn secret_is_read_transparently_and_never_printed() {
+    let config: Config = serde_json::from_str(
+        r#"{ "name": "pws", "sign_key": "sign-key-value", "admin_token": "admin-token-value" }"#,
+    )
+    .unwrap();
+    assert_eq!(config.sign_key.expose(), "sign-key-value");
+    assert_eq!(
+        config.admin_token.as_ref().map(|t| t.expose().as_str()),
+        Some("admin-token-value")
+    );
+
+    let outputs = [
+        format!("{:?}", config),
+        format!("{:#?}", config),
+        format!("{}", config.sign_key),
+        serde_json::to_string(&config).unwrap(),
+    ];
+    for output in outputs {
+        assert!(output.contains(REDACTED), "{}", output);
+        assert!(!output.contains("sign-key-value"), "{}", output);
+        assert!(!output.contains("admin-token-value"), "{}", output);
+    }
+}
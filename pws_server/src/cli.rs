@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Phi-WebHook-Server")]
+pub struct Cli {
+    /// 配置文件路径, `.toml` 或 `.json`; 未指定且 `./config.json` 不存在时只读取 `PWS_*` 环境变量
+    #[arg(short, long, global = true, env = "PWS_CONFIG")]
+    pub config: Option<String>,
+    /// 未指定子命令时等同于 `serve`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 启动 HTTP 服务
+    Serve,
+    /// 将本地存档压缩包导入存储, 导入前会先完整解码校验
+    ImportSave {
+        openid: String,
+        zip: PathBuf,
+        /// 同时写入用户表的昵称
+        #[arg(long)]
+        nickname: Option<String>,
+        /// 记录到存档元数据的 file_object_id, 默认为文件名
+        #[arg(long)]
+        file_object_id: Option<String>,
+    },
+    /// 导出存储中的原始存档压缩包
+    Export {
+        openid: String,
+        /// 输出路径, 默认为 `<openid>.zip`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 离线解码存档压缩包并输出 JSON, 不需要配置文件
+    Decode { zip: PathBuf },
+    /// 用配置中的 sign_key 计算请求体的 X-Sign, `-` 表示标准输入
+    Sign { file: PathBuf },
+    /// 列出用户表
+    Users,
+    /// 将全部表与存档文件导出为 NDJSON, 可用于备份或迁移到其他后端
+    Dump {
+        /// 输出路径, `-` 表示标准输出
+        #[arg(short, long, default_value = "-")]
+        output: PathBuf,
+    },
+    /// 导入 `dump` 的输出, 已存在的键会被覆盖
+    Load {
+        /// 输入路径, `-` 表示标准输入
+        #[arg(default_value = "-")]
+        input: PathBuf,
+    },
+    /// 把当前配置的存储完整复制到另一份配置指定的存储, 如从 redb 迁移到 sqlite
+    Migrate {
+        /// 目标存储所用的配置文件, 只读取 `kv_backend`, `kv_storage_path` 与 `blobs`
+        target: String,
+    },
+    /// 校验 redb 快照并替换当前数据库, 需先停止服务. 原数据库改名保留为 `.bak`
+    Restore {
+        snapshot: PathBuf,
+        /// 只校验, 不替换
+        #[arg(long)]
+        check: bool,
+        /// 快照引用的存档文件已被回收时仍然恢复
+        #[arg(long)]
+        force: bool,
+    },
+    /// 立即按 `retention` 配置删除过期存档与多余的历史存档
+    Retention {
+        /// 只列出将被删除的存档, 不做改动; 配置中的 `retention.dry_run` 同样生效
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 清空成绩索引并从已存储的全部存档重新生成
+    RebuildIndex,
+    /// 立即回收不再被引用的存档文件 (写入不足 `blobs.gc_grace_secs` 的除外) 并清除过期记录
+    Gc,
+}
@@ -0,0 +1,350 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pws_core::dump;
+use pws_core::error::Error;
+use pws_core::i18n::{Lang, Msg};
+use pws_core::index;
+use pws_core::retention;
+use pws_core::save;
+use pws_core::sign::sign;
+use pws_core::store;
+
+use crate::config::{Config, StorageConfig};
+use crate::exit_with;
+use crate::kv::Storage;
+use crate::snapshot::Restore;
+use crate::types::KvBackend;
+
+fn read_file(path: &Path, lang: Lang) -> Vec<u8> {
+    let result = if path == Path::new("-") {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf).map(|_| buf)
+    } else {
+        fs::read(path)
+    };
+    result.unwrap_or_else(|e| {
+        exit_with(
+            Msg::FileReadFailed {
+                path: &path.display().to_string(),
+                error: &e.to_string(),
+            },
+            lang,
+        )
+    })
+}
+
+fn open_kv(config: &Config) -> Storage {
+    open_storage(&config.storage(), config.log_lang)
+}
+
+fn open_storage(config: &StorageConfig, lang: Lang) -> Storage {
+    Storage::open(config)
+        .unwrap_or_else(|e| exit_with(Msg::StorageOpenFailed(&e.to_string()), lang))
+}
+
+fn check(result: Result<(), Error>, lang: Lang) {
+    if let Err(e) = result {
+        exit_with(e.msg(), lang)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub async fn import_save(
+    config: &Config,
+    openid: &str,
+    zip: &Path,
+    nickname: Option<&str>,
+    file_object_id: Option<&str>,
+) {
+    let lang = config.log_lang;
+    let data = read_file(zip, lang);
+    if let Err(e) = save::decode(&data) {
+        exit_with(e.msg(), lang)
+    }
+
+    let file_object_id = file_object_id
+        .map(str::to_owned)
+        .or_else(|| zip.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+
+    let kv = open_kv(config);
+    let now = now();
+    check(
+        store::put_save(
+            &kv,
+            openid,
+            &file_object_id,
+            &data,
+            None,
+            now,
+            config.retention.keep_history,
+        )
+        .await
+        .map(drop),
+        lang,
+    );
+    if let Some(nickname) = nickname {
+        check(store::put_user(&kv, openid, nickname, now).await, lang);
+    }
+    eprintln!(
+        "{}",
+        Msg::SaveImported {
+            openid,
+            size: data.len()
+        }
+        .text(lang)
+    );
+}
+
+pub async fn export(config: &Config, openid: &str, output: Option<&Path>) {
+    let lang = config.log_lang;
+    let kv = open_kv(config);
+    let data = match store::get_save(&kv, openid).await {
+        Ok(Some(data)) => data,
+        Ok(None) => exit_with(Msg::NotFound, lang),
+        Err(e) => exit_with(e.msg(), lang),
+    };
+
+    let default = format!("{}.zip", openid);
+    let path = output.unwrap_or(Path::new(&default)).display().to_string();
+    if let Err(e) = fs::write(&path, &data) {
+        exit_with(
+            Msg::FileWriteFailed {
+                path: &path,
+                error: &e.to_string(),
+            },
+            lang,
+        )
+    }
+    eprintln!(
+        "{}",
+        Msg::SaveExported {
+            path: &path,
+            size: data.len()
+        }
+        .text(lang)
+    );
+}
+
+pub fn decode(zip: &Path) {
+    let lang = Lang::default();
+    let save = save::decode(&read_file(zip, lang)).unwrap_or_else(|e| exit_with(e.msg(), lang));
+    let json = serde_json::to_string_pretty(&save)
+        .unwrap_or_else(|e| exit_with(Msg::EncodeError(&e.to_string()), lang));
+    println!("{}", json);
+}
+
+pub fn sign_file(config: &Config, file: &Path) {
+    let body = read_file(file, config.log_lang);
+    println!("{}", sign(config.sign_key.expose().as_bytes(), &body));
+}
+
+pub async fn users(config: &Config) {
+    let kv = open_kv(config);
+    let users = store::list_users(&kv)
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), config.log_lang));
+    for (openid, user) in users {
+        let updated_at = user.updated_at.map_or("-".to_owned(), |t| t.to_string());
+        println!("{}\t{}\t{}", openid, user.nickname, updated_at);
+    }
+}
+
+pub async fn gc(config: &Config) {
+    let lang = config.log_lang;
+    let kv = open_kv(config);
+    let before = now().saturating_sub(config.blobs.gc_grace_secs.saturating_mul(1000));
+    let removed = store::collect_blobs(&kv, before)
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    for hash in &removed {
+        println!("{}", hash);
+    }
+    eprintln!("{}", Msg::BlobsCollected(removed.len()).text(lang));
+    let swept = kv
+        .sweep_expired(now())
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    check(kv.flush(), lang);
+    eprintln!("{}", Msg::ExpiredSwept(swept).text(lang));
+}
+
+pub async fn retention(config: &Config, dry_run: bool) {
+    let lang = config.log_lang;
+    let kv = open_kv(config);
+    let dry_run = dry_run || config.retention.dry_run;
+    let report = retention::enforce(&kv, &config.retention.policy(), now(), dry_run)
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    check(kv.flush(), lang);
+    for expired in &report.expired {
+        println!("{}\t{}", expired.openid, expired.updated_at);
+    }
+    eprintln!(
+        "{}",
+        Msg::RetentionReport {
+            dry_run,
+            expired: report.expired.len(),
+            trimmed: report.trimmed,
+            freed_blobs: report.freed_blobs,
+            freed_bytes: report.freed_bytes
+        }
+        .text(lang)
+    );
+}
+
+pub async fn rebuild_index(config: &Config) {
+    let lang = config.log_lang;
+    let kv = open_kv(config);
+    let rebuilt = index::rebuild(&kv)
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    check(kv.flush(), lang);
+    for (openid, error) in &rebuilt.skipped {
+        eprintln!(
+            "{}",
+            Msg::RecordIndexSkippedUser {
+                openid,
+                error: &error.message(lang)
+            }
+            .text(lang)
+        );
+    }
+    eprintln!(
+        "{}",
+        Msg::RecordIndexRebuilt {
+            users: rebuilt.users,
+            records: rebuilt.records,
+            skipped: rebuilt.skipped.len()
+        }
+        .text(lang)
+    );
+}
+
+pub async fn dump(config: &Config, output: &Path) {
+    let lang = config.log_lang;
+    let kv = open_kv(config);
+    let out: Box<dyn Write> = if output == Path::new("-") {
+        Box::new(io::stdout())
+    } else {
+        match File::create(output) {
+            Ok(file) => Box::new(file),
+            Err(e) => exit_with(
+                Msg::FileWriteFailed {
+                    path: &output.display().to_string(),
+                    error: &e.to_string(),
+                },
+                lang,
+            ),
+        }
+    };
+    let stats = dump::export(&kv, BufWriter::new(out))
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    eprintln!(
+        "{}",
+        Msg::DumpExported {
+            records: stats.records,
+            blobs: stats.blobs
+        }
+        .text(lang)
+    );
+}
+
+pub async fn load(config: &Config, input: &Path) {
+    let lang = config.log_lang;
+    let source: Box<dyn Read> = if input == Path::new("-") {
+        Box::new(io::stdin())
+    } else {
+        match File::open(input) {
+            Ok(file) => Box::new(file),
+            Err(e) => exit_with(
+                Msg::FileReadFailed {
+                    path: &input.display().to_string(),
+                    error: &e.to_string(),
+                },
+                lang,
+            ),
+        }
+    };
+    let kv = open_kv(config);
+    let result = dump::import(&kv, BufReader::new(source)).await;
+    // 失败时已写入的部分同样需要落盘
+    check(kv.flush(), lang);
+    let stats = result.unwrap_or_else(|e| exit_with(e.msg(), lang));
+    eprintln!(
+        "{}",
+        Msg::DumpImported {
+            records: stats.records,
+            blobs: stats.blobs
+        }
+        .text(lang)
+    );
+}
+
+pub async fn migrate(config: &Config, target: &str) {
+    let lang = config.log_lang;
+    let target = StorageConfig::load(Some(target)).unwrap_or_else(|e| exit_with(e.msg(), lang));
+    let from = open_kv(config);
+    let to = open_storage(&target, lang);
+    let result = dump::copy(&from, &to).await;
+    check(to.flush(), lang);
+    let stats = result.unwrap_or_else(|e| exit_with(e.msg(), lang));
+    eprintln!(
+        "{}",
+        Msg::DumpImported {
+            records: stats.records,
+            blobs: stats.blobs
+        }
+        .text(lang)
+    );
+}
+
+pub async fn restore(config: &Config, snapshot: &Path, check_only: bool, force: bool) {
+    let lang = config.log_lang;
+    if config.kv_backend != KvBackend::Redb {
+        exit_with(Msg::SnapshotRedbOnly, lang)
+    }
+    let path = snapshot.display().to_string();
+    let mut restore = Restore::prepare(snapshot, &config.storage())
+        .await
+        .unwrap_or_else(|e| exit_with(e.msg(), lang));
+    eprintln!(
+        "{}",
+        Msg::SnapshotVerified {
+            path: &path,
+            records: restore.records
+        }
+        .text(lang)
+    );
+    // 退出进程前先丢弃 restore, 删除暂存的副本并释放锁
+    if restore.missing > 0 && !force {
+        let missing = restore.missing;
+        drop(restore);
+        exit_with(Msg::SnapshotMissingBlobs(missing), lang)
+    }
+    if check_only {
+        return;
+    }
+
+    let result = restore.apply(now());
+    drop(restore);
+    let (blobs, backup) = result.unwrap_or_else(|e| exit_with(e.msg(), lang));
+    if blobs > 0 {
+        eprintln!("{}", Msg::SnapshotBlobsRestored(blobs).text(lang));
+    }
+    eprintln!("{}", Msg::SnapshotRestored(&path).text(lang));
+    if let Some(backup) = backup {
+        eprintln!(
+            "{}",
+            Msg::SnapshotBackedUp(&backup.display().to_string()).text(lang)
+        );
+    }
+}
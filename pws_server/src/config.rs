@@ -0,0 +1,608 @@
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use pws_core::i18n::{Lang, Msg};
+use pws_core::retention::RetentionPolicy;
+use pws_core::secret::Secret;
+use pws_core::sign::MAX_KEY_LEN;
+use pws_core::types::LogLevel;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::types::{KvBackend, LogFormat};
+
+pub const CONFIG_PATH: &str = "./config.json";
+pub const ENV_PREFIX: &str = "PWS_";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Config {
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub log_lang: Lang,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub kv_backend: KvBackend,
+    pub kv_storage_path: String,
+    pub sign_key: Secret,
+    pub file_url_template: String,
+    #[serde(default)]
+    pub admin_token: Option<Secret>,
+    /// 单独暴露 `/metrics` 的监听地址, 为空时挂在主端口上
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+    #[serde(default)]
+    pub blobs: BlobConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 设置后定期快照 redb 数据库
+    #[serde(default)]
+    pub snapshots: Option<SnapshotConfig>,
+    /// 设置后主端口改为 HTTPS
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// 单个请求的处理时限, 单位秒
+    pub request_timeout_secs: u64,
+    /// 请求体上限, 单位字节
+    pub body_limit: usize,
+    /// 收到关闭信号后等待进行中请求完成的时限, 单位秒
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_owned(),
+            port: 3000,
+            request_timeout_secs: 30,
+            body_limit: 2 * 1024 * 1024,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
+
+/// 拉取存档文件所用的 HTTP 客户端
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HttpClientConfig {
+    /// 跳过上游证书校验, 仅用于自签名证书的测试环境
+    pub accept_invalid_certs: bool,
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            accept_invalid_certs: false,
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// 存档文件按内容哈希存放的目录与回收策略
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BlobConfig {
+    pub path: String,
+    /// 回收未被引用 blob 的间隔, 单位秒
+    pub gc_interval_secs: u64,
+    /// 写入后这段时间内的 blob 不会被回收, 单位秒
+    pub gc_grace_secs: u64,
+}
+
+impl Default for BlobConfig {
+    fn default() -> Self {
+        Self {
+            path: "./blobs".to_owned(),
+            gc_interval_secs: 3600,
+            gc_grace_secs: 3600,
+        }
+    }
+}
+
+/// 数据保留策略与定期执行的间隔
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// 超过这么多天未更新的存档会被删除, 为空时不按时间清理
+    pub max_age_days: Option<u64>,
+    /// 每个用户保留的历史存档数量
+    pub keep_history: usize,
+    /// 执行清理的间隔, 单位秒
+    pub interval_secs: u64,
+    /// 只记录将被清理的内容, 不实际删除
+    pub dry_run: bool,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: None,
+            keep_history: 0,
+            interval_secs: 24 * 3600,
+            dry_run: false,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_days: self.max_age_days,
+            keep_history: self.keep_history,
+        }
+    }
+}
+
+/// redb 数据库的定期快照, 仅支持 `kv_backend = "redb"`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotConfig {
+    /// 存放快照的目录
+    pub path: String,
+    /// 两次快照的间隔, 单位秒
+    #[serde(default = "default_snapshot_interval")]
+    pub interval_secs: u64,
+    /// 保留的快照数量, 超出时删除最旧的
+    #[serde(default = "default_snapshot_keep")]
+    pub keep: usize,
+}
+
+fn default_snapshot_interval() -> u64 {
+    24 * 3600
+}
+
+fn default_snapshot_keep() -> usize {
+    7
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM 格式的证书链
+    pub cert_path: String,
+    /// PEM 格式的私钥
+    pub key_path: String,
+    /// 检查证书文件是否变化的间隔, 单位秒
+    #[serde(default = "default_reload_interval")]
+    pub reload_interval_secs: u64,
+    /// 额外监听的明文端口, 所有请求重定向到 HTTPS
+    #[serde(default)]
+    pub redirect_port: Option<u16>,
+    /// 重定向目标的主机名, 不含端口. 设置 `redirect_port` 时必填, 不信任请求中的 `Host`
+    #[serde(default)]
+    pub public_host: Option<String>,
+}
+
+fn default_reload_interval() -> u64 {
+    60
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: String, error: String },
+    Parse { path: String, error: String },
+    Env { name: String, value: String },
+    Empty(&'static str),
+    NotPositive(&'static str),
+    MissingPlaceholder(String),
+    Invalid { field: &'static str, error: String },
+}
+
+impl ConfigError {
+    pub fn msg(&self) -> Msg<'_> {
+        match self {
+            ConfigError::Read { path, error } => Msg::ConfigReadFailed { path, error },
+            ConfigError::Parse { path, error } => Msg::ConfigParseFailed { path, error },
+            ConfigError::Env { name, value } => Msg::EnvInvalid { name, value },
+            ConfigError::Empty(field) => Msg::ConfigFieldEmpty(field),
+            ConfigError::NotPositive(field) => Msg::ConfigNotPositive(field),
+            ConfigError::MissingPlaceholder(template) => Msg::TemplateMissingPlaceholder(template),
+            ConfigError::Invalid { field, error } => Msg::ConfigFieldInvalid { field, error },
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Str,
+    Int,
+    Bool,
+}
+
+/// 可被环境变量覆盖的配置项, 变量名为 `PWS_` 加上以 `_` 连接的大写路径,
+/// 如 `PWS_SIGN_KEY`, `PWS_SERVER_PORT`
+const ENV_KEYS: &[(&[&str], Kind)] = &[
+    (&["log_level"], Kind::Str),
+    (&["log_lang"], Kind::Str),
+    (&["log_format"], Kind::Str),
+    (&["kv_backend"], Kind::Str),
+    (&["kv_storage_path"], Kind::Str),
+    (&["sign_key"], Kind::Str),
+    (&["file_url_template"], Kind::Str),
+    (&["admin_token"], Kind::Str),
+    (&["metrics_addr"], Kind::Str),
+    (&["server", "host"], Kind::Str),
+    (&["server", "port"], Kind::Int),
+    (&["server", "request_timeout_secs"], Kind::Int),
+    (&["server", "body_limit"], Kind::Int),
+    (&["server", "shutdown_timeout_secs"], Kind::Int),
+    (&["http_client", "accept_invalid_certs"], Kind::Bool),
+    (&["http_client", "connect_timeout_secs"], Kind::Int),
+    (&["http_client", "timeout_secs"], Kind::Int),
+    (&["blobs", "path"], Kind::Str),
+    (&["blobs", "gc_interval_secs"], Kind::Int),
+    (&["blobs", "gc_grace_secs"], Kind::Int),
+    (&["retention", "max_age_days"], Kind::Int),
+    (&["retention", "keep_history"], Kind::Int),
+    (&["retention", "interval_secs"], Kind::Int),
+    (&["retention", "dry_run"], Kind::Bool),
+    (&["snapshots", "path"], Kind::Str),
+    (&["snapshots", "interval_secs"], Kind::Int),
+    (&["snapshots", "keep"], Kind::Int),
+    (&["tls", "cert_path"], Kind::Str),
+    (&["tls", "key_path"], Kind::Str),
+    (&["tls", "reload_interval_secs"], Kind::Int),
+    (&["tls", "redirect_port"], Kind::Int),
+    (&["tls", "public_host"], Kind::Str),
+];
+
+fn env_name(path: &[&str]) -> String {
+    format!("{}{}", ENV_PREFIX, path.join("_").to_ascii_uppercase())
+}
+
+fn env_value(kind: Kind, raw: String) -> Option<Value> {
+    match kind {
+        Kind::Str => Some(Value::String(raw)),
+        Kind::Int => raw.trim().parse::<u64>().ok().map(Value::from),
+        Kind::Bool => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+fn apply_env(config: &mut Value) -> Result<(), ConfigError> {
+    for &(path, kind) in ENV_KEYS {
+        let name = env_name(path);
+        let Ok(raw) = env::var(&name) else { continue };
+        let value = env_value(kind, raw.clone()).ok_or(ConfigError::Env { name, value: raw })?;
+
+        let (key, parents) = path.split_last().expect("empty config path");
+        let mut node = &mut *config;
+        for parent in parents {
+            node = node
+                .as_object_mut()
+                .expect("config root is an object")
+                .entry(*parent)
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        if let Some(object) = node.as_object_mut() {
+            object.insert((*key).to_owned(), value);
+        }
+    }
+    Ok(())
+}
+
+/// 按扩展名选择格式, `.toml` 以外一律按 JSON 解析
+fn read_file(path: &str) -> Result<Value, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|e| ConfigError::Read {
+        path: path.to_owned(),
+        error: e.to_string(),
+    })?;
+    let is_toml = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    let parsed = if is_toml {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|error| ConfigError::Parse {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+fn show<T: PartialEq + Debug>(changes: &mut Vec<String>, field: &str, old: &T, new: &T) {
+    if old != new {
+        changes.push(format!("{}: {:?} -> {:?}", field, old, new));
+    }
+}
+
+/// 读取配置文件并应用环境变量覆盖, 解析为 `T`.
+/// 未指定路径且默认路径不存在时只从环境变量读取.
+fn parse<T: DeserializeOwned>(path: Option<&str>) -> Result<T, ConfigError> {
+    let (source, mut value) = match path {
+        Some(path) => (path.to_owned(), read_file(path)?),
+        None if Path::new(CONFIG_PATH).exists() => {
+            (CONFIG_PATH.to_owned(), read_file(CONFIG_PATH)?)
+        }
+        None => ("<env>".to_owned(), Value::Object(Map::new())),
+    };
+    if !value.is_object() {
+        return Err(ConfigError::Parse {
+            path: source,
+            error: "expected a table at the top level".to_owned(),
+        });
+    }
+
+    apply_env(&mut value)?;
+    serde_json::from_value(value).map_err(|e| ConfigError::Parse {
+        path: source,
+        error: e.to_string(),
+    })
+}
+
+/// 打开存储所需的配置项, `migrate` 的目标配置只需要这些
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub kv_backend: KvBackend,
+    pub kv_storage_path: String,
+    #[serde(default)]
+    pub blobs: BlobConfig,
+}
+
+/// `tls.public_host` 只能是主机名, 不含协议, 端口与路径
+fn validate_host(host: &str) -> Result<(), ConfigError> {
+    let invalid = |error: String| ConfigError::Invalid {
+        field: "tls.public_host",
+        error,
+    };
+    let url = Url::parse(&format!("https://{}", host)).map_err(|e| invalid(e.to_string()))?;
+    if url.host_str() != Some(host.to_ascii_lowercase().as_str()) {
+        return Err(invalid(format!("{:?} is not a bare host name", host)));
+    }
+    Ok(())
+}
+
+impl StorageConfig {
+    /// 与 [`Config::load`] 读取方式相同, 但只解析并校验存储相关的配置项
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        let config: StorageConfig = parse(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        for (field, value) in [
+            ("kv_storage_path", &self.kv_storage_path),
+            ("blobs.path", &self.blobs.path),
+        ] {
+            if value.is_empty() {
+                return Err(ConfigError::Empty(field));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// 读取配置文件并应用环境变量覆盖, 最后做校验.
+    /// 未指定路径且默认路径不存在时只从环境变量读取.
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        let config: Config = parse(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn storage(&self) -> StorageConfig {
+        StorageConfig {
+            kv_backend: self.kv_backend,
+            kv_storage_path: self.kv_storage_path.clone(),
+            blobs: self.blobs.clone(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.storage().validate()?;
+        if self.log_level == LogLevel::UNKNOWN {
+            return Err(ConfigError::Invalid {
+                field: "log_level",
+                error: "expected one of DEBUG, INFO, WARN, ERROR".to_owned(),
+            });
+        }
+        if self.sign_key.expose().is_empty() {
+            return Err(ConfigError::Empty("sign_key"));
+        }
+        if self.sign_key.expose().len() > MAX_KEY_LEN {
+            return Err(ConfigError::Invalid {
+                field: "sign_key",
+                error: format!("longer than {} bytes", MAX_KEY_LEN),
+            });
+        }
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|t| t.expose().is_empty())
+        {
+            return Err(ConfigError::Empty("admin_token"));
+        }
+
+        if !self.file_url_template.contains("{file_obj_id}") {
+            return Err(ConfigError::MissingPlaceholder(
+                self.file_url_template.clone(),
+            ));
+        }
+        Url::parse(&self.file_url_template.replace("{file_obj_id}", "0")).map_err(|e| {
+            ConfigError::Invalid {
+                field: "file_url_template",
+                error: e.to_string(),
+            }
+        })?;
+
+        self.server
+            .host
+            .parse::<IpAddr>()
+            .map_err(|e| ConfigError::Invalid {
+                field: "server.host",
+                error: e.to_string(),
+            })?;
+        if let Some(addr) = &self.metrics_addr {
+            addr.parse::<SocketAddr>()
+                .map_err(|e| ConfigError::Invalid {
+                    field: "metrics_addr",
+                    error: e.to_string(),
+                })?;
+        }
+
+        for (field, value) in [
+            (
+                "server.request_timeout_secs",
+                self.server.request_timeout_secs,
+            ),
+            ("server.body_limit", self.server.body_limit as u64),
+            (
+                "server.shutdown_timeout_secs",
+                self.server.shutdown_timeout_secs,
+            ),
+            (
+                "http_client.connect_timeout_secs",
+                self.http_client.connect_timeout_secs,
+            ),
+            ("http_client.timeout_secs", self.http_client.timeout_secs),
+            ("blobs.gc_interval_secs", self.blobs.gc_interval_secs),
+            ("retention.interval_secs", self.retention.interval_secs),
+            (
+                "retention.max_age_days",
+                self.retention.max_age_days.unwrap_or(1),
+            ),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::NotPositive(field));
+            }
+        }
+
+        if let Some(snapshots) = &self.snapshots {
+            if snapshots.path.is_empty() {
+                return Err(ConfigError::Empty("snapshots.path"));
+            }
+            for (field, value) in [
+                ("snapshots.interval_secs", snapshots.interval_secs),
+                ("snapshots.keep", snapshots.keep as u64),
+            ] {
+                if value == 0 {
+                    return Err(ConfigError::NotPositive(field));
+                }
+            }
+            if self.kv_backend != KvBackend::Redb {
+                return Err(ConfigError::Invalid {
+                    field: "snapshots",
+                    error: "only supported with kv_backend \"redb\"".to_owned(),
+                });
+            }
+        }
+
+        if let Some(tls) = &self.tls {
+            for (field, value) in [
+                ("tls.cert_path", &tls.cert_path),
+                ("tls.key_path", &tls.key_path),
+            ] {
+                if value.is_empty() {
+                    return Err(ConfigError::Empty(field));
+                }
+            }
+            if tls.reload_interval_secs == 0 {
+                return Err(ConfigError::NotPositive("tls.reload_interval_secs"));
+            }
+            if tls.redirect_port == Some(self.server.port) {
+                return Err(ConfigError::Invalid {
+                    field: "tls.redirect_port",
+                    error: format!("conflicts with server.port {}", self.server.port),
+                });
+            }
+            match &tls.public_host {
+                Some(host) if host.is_empty() => {
+                    return Err(ConfigError::Empty("tls.public_host"));
+                }
+                Some(host) => validate_host(host)?,
+                None if tls.redirect_port.is_some() => {
+                    return Err(ConfigError::Empty("tls.public_host"));
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn bind_addr(&self) -> SocketAddr {
+        let host = self.server.host.parse().expect("validated in Config::load");
+        SocketAddr::new(host, self.server.port)
+    }
+
+    /// 对比两份配置, 返回已可热更新的变化描述与需要重启才能生效的配置项.
+    /// 密钥类只记录是否变化, 不输出内容.
+    pub fn diff(&self, new: &Config) -> (Vec<String>, Vec<&'static str>) {
+        let mut changes = Vec::new();
+        show(&mut changes, "log_level", &self.log_level, &new.log_level);
+        show(&mut changes, "log_lang", &self.log_lang, &new.log_lang);
+        show(
+            &mut changes,
+            "file_url_template",
+            &self.file_url_template,
+            &new.file_url_template,
+        );
+        show(
+            &mut changes,
+            "http_client",
+            &self.http_client,
+            &new.http_client,
+        );
+        // Secret 只显示为占位符
+        if self.sign_key != new.sign_key {
+            changes.push(format!("sign_key: {}", new.sign_key));
+        }
+        if self.admin_token != new.admin_token {
+            changes.push(format!("admin_token: {:?}", new.admin_token));
+        }
+
+        let restart = [
+            ("log_format", self.log_format != new.log_format),
+            ("kv_backend", self.kv_backend != new.kv_backend),
+            (
+                "kv_storage_path",
+                self.kv_storage_path != new.kv_storage_path,
+            ),
+            ("metrics_addr", self.metrics_addr != new.metrics_addr),
+            ("blobs", self.blobs != new.blobs),
+            ("server", self.server != new.server),
+            ("retention", self.retention != new.retention),
+            ("snapshots", self.snapshots != new.snapshots),
+            ("tls", self.tls != new.tls),
+        ]
+        .into_iter()
+        .filter_map(|(field, changed)| changed.then_some(field))
+        .collect();
+
+        (changes, restart)
+    }
+}
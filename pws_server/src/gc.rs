@@ -0,0 +1,41 @@
+use std::sync::Weak;
+use std::time::Duration;
+
+use pws_core::i18n::Msg;
+use pws_core::store;
+use pws_core::types::{AppState, AppUtils};
+
+use crate::config::BlobConfig;
+use crate::kv::Storage;
+use crate::utils::ServerUtils;
+
+type State = Weak<AppState<ServerUtils, Storage>>;
+
+/// 定期回收不再被任何存档引用的 blob, 并清除过期的 KV 记录.
+/// 只持有弱引用, 不妨碍关闭时释放存储.
+pub fn spawn(config: &BlobConfig, state: State) {
+    let interval = Duration::from_secs(config.gc_interval_secs);
+    let grace = config.gc_grace_secs.saturating_mul(1000);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(state) = state.upgrade() else { break };
+            let lang = state.utils.log_lang();
+            let before = state.utils.now().saturating_sub(grace);
+            match store::collect_blobs(&state.kv, before).await {
+                Ok(removed) if removed.is_empty() => {}
+                Ok(removed) => {
+                    tracing::info!("{}", Msg::BlobsCollected(removed.len()).text(lang));
+                }
+                Err(e) => tracing::error!("{}", e.message(lang)),
+            }
+            match state.kv.sweep_expired(state.utils.now()) {
+                Ok(0) => {}
+                Ok(swept) => tracing::info!("{}", Msg::ExpiredSwept(swept).text(lang)),
+                Err(e) => tracing::error!("{}", e.message(lang)),
+            }
+        }
+    });
+}
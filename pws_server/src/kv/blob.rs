@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use pws_core::error::Error;
+use pws_core::types::{BlobInfo, BlobStore};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::storage_err;
+use crate::metrics::METRICS;
+
+const TMP_DIR: &str = "tmp";
+
+fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// 以哈希为文件名保存 blob, 按前两位分目录, 写入时先落到 `tmp/` 再原子改名
+#[derive(Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join(TMP_DIR))?;
+        Ok(Self { root })
+    }
+
+    /// 就绪检查, 确认 blob 目录仍然存在
+    pub fn ready(&self) -> Result<(), Error> {
+        fs::metadata(self.root.join(TMP_DIR))
+            .map(drop)
+            .map_err(storage_err)
+    }
+
+    fn path(&self, hash: &str) -> Result<PathBuf, Error> {
+        if !is_hash(hash) {
+            return Err(Error::Storage(format!("invalid blob hash {:?}", hash)));
+        }
+        Ok(self.root.join(&hash[..2]).join(hash))
+    }
+
+    /// 把 `hash` 硬链接到 `dest` 中, 硬链接失败 (如跨文件系统) 时改为复制.
+    /// 返回本地是否存在该 blob.
+    pub fn link_to(&self, hash: &str, dest: &FsBlobStore) -> Result<bool, Error> {
+        let source = self.path(hash)?;
+        let target = dest.path(hash)?;
+        if !source.exists() {
+            return Ok(false);
+        }
+        if target.exists() {
+            return Ok(true);
+        }
+        fs::create_dir_all(target.parent().expect("blob path has a parent"))
+            .map_err(storage_err)?;
+        match fs::hard_link(&source, &target) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(true),
+            Err(_) => match self.get_inner(hash)? {
+                Some(data) => dest.put_inner(hash, &data).map(|()| true),
+                None => Ok(false),
+            },
+        }
+    }
+
+    fn get_inner(&self, hash: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.path(hash)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_err(e)),
+        }
+    }
+
+    fn put_inner(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        let path = self.path(hash)?;
+        if path.exists() {
+            // 刷新修改时间, 避免刚被重新引用的 blob 在回收时被误删
+            return File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(SystemTime::now()))
+                .map_err(storage_err);
+        }
+
+        let tmp = self
+            .root
+            .join(TMP_DIR)
+            .join(uuid::Uuid::new_v4().simple().to_string());
+        let written = (|| {
+            let mut file = File::create(&tmp)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+            fs::create_dir_all(path.parent().expect("blob path has a parent"))?;
+            fs::rename(&tmp, &path)
+        })();
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written.map_err(storage_err)
+    }
+
+    fn delete_inner(&self, hash: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(hash)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(storage_err(e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn modified_inner(&self, hash: &str) -> Result<Option<u64>, Error> {
+        match fs::metadata(self.path(hash)?).and_then(|m| m.modified()) {
+            Ok(modified) => Ok(Some(millis(modified))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_err(e)),
+        }
+    }
+
+    fn list_inner(&self) -> Result<Vec<BlobInfo>, Error> {
+        let mut blobs = Vec::new();
+        for dir in fs::read_dir(&self.root).map_err(storage_err)? {
+            let dir = dir.map_err(storage_err)?;
+            if dir.file_name() == TMP_DIR || !dir.file_type().map_err(storage_err)?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(dir.path()).map_err(storage_err)? {
+                let entry = entry.map_err(storage_err)?;
+                let Some(hash) = entry
+                    .file_name()
+                    .to_str()
+                    .filter(|h| is_hash(h))
+                    .map(str::to_owned)
+                else {
+                    continue;
+                };
+                let modified = entry.metadata().and_then(|m| m.modified()).ok().map(millis);
+                blobs.push(BlobInfo { hash, modified });
+            }
+        }
+        Ok(blobs)
+    }
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, Error> {
+        let start = Instant::now();
+        let result = self.get_inner(hash);
+        METRICS.observe_kv("blob", "get", start);
+        result
+    }
+
+    async fn put(&self, hash: &str, data: &[u8]) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.put_inner(hash, data);
+        METRICS.observe_kv("blob", "put", start);
+        result
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.delete_inner(hash);
+        METRICS.observe_kv("blob", "delete", start);
+        result
+    }
+
+    async fn list(&self) -> Result<Vec<BlobInfo>, Error> {
+        let start = Instant::now();
+        let result = self.list_inner();
+        METRICS.observe_kv("blob", "list", start);
+        result
+    }
+
+    async fn modified(&self, hash: &str) -> Result<Option<u64>, Error> {
+        let start = Instant::now();
+        let result = self.modified_inner(hash);
+        METRICS.observe_kv("blob", "modified", start);
+        result
+    }
+}
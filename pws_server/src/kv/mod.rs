@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use pws_core::error::Error;
+use pws_core::types::{Entry, KVStorage, KVTable, Page, PutOptions};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::StorageConfig;
+use crate::types::KvBackend;
+
+mod blob;
+mod redb;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use self::blob::FsBlobStore;
+pub use self::redb::{FileLock, Locked, RedbKVStorage, RedbKVTable};
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::{SqliteKVStorage, SqliteKVTable};
+
+fn storage_err(e: impl std::fmt::Display) -> Error {
+    Error::Storage(e.to_string())
+}
+
+fn decode_options(raw: &[u8]) -> Result<PutOptions, Error> {
+    serde_json::from_slice(raw).map_err(storage_err)
+}
+
+/// 判断过期使用的当前时间, Unix 毫秒
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// 按 `kv_backend` 选择的 KV 后端
+pub enum Backend {
+    Redb(RedbKVStorage),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteKVStorage),
+}
+
+pub enum Table {
+    Redb(RedbKVTable),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteKVTable),
+}
+
+/// KV 后端与存放存档文件的 blob 目录
+pub struct Storage {
+    backend: Backend,
+    blobs: FsBlobStore,
+}
+
+impl Storage {
+    pub fn open(config: &StorageConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = config.kv_storage_path.clone();
+        let backend = match config.kv_backend {
+            KvBackend::Redb => Backend::Redb(RedbKVStorage::new(path)?),
+            #[cfg(feature = "sqlite")]
+            KvBackend::Sqlite => Backend::Sqlite(SqliteKVStorage::new(&path)?),
+        };
+        let blobs = FsBlobStore::new(&config.blobs.path)?;
+        Ok(Self::new(backend, blobs))
+    }
+
+    pub fn new(backend: Backend, blobs: FsBlobStore) -> Self {
+        Self { backend, blobs }
+    }
+
+    /// 当前后端为 redb 时返回它, 用于快照
+    pub fn redb(&self) -> Option<&RedbKVStorage> {
+        match &self.backend {
+            Backend::Redb(kv) => Some(kv),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(_) => None,
+        }
+    }
+
+    /// 删除在 `now` 之前过期的记录, 返回删除的数量. 读取时已隐藏过期记录, 这里只回收空间.
+    pub fn sweep_expired(&self, now: u64) -> Result<u64, Error> {
+        match &self.backend {
+            Backend::Redb(kv) => kv.sweep_expired(now),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(kv) => kv.sweep_expired(now),
+        }
+    }
+
+    /// 确保此前的写入全部落盘
+    pub fn flush(&self) -> Result<(), Error> {
+        match &self.backend {
+            Backend::Redb(kv) => kv.flush(),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(kv) => kv.flush(),
+        }
+    }
+}
+
+#[async_trait]
+impl KVStorage for Storage {
+    type Table = Table;
+    type Blobs = FsBlobStore;
+
+    async fn open_table(&self, table: &str) -> Result<Self::Table, Error> {
+        Ok(match &self.backend {
+            Backend::Redb(kv) => Table::Redb(kv.open_table(table)?),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(kv) => Table::Sqlite(kv.open_table(table)?),
+        })
+    }
+
+    fn blobs(&self) -> &Self::Blobs {
+        &self.blobs
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        match &self.backend {
+            Backend::Redb(kv) => kv.ready()?,
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(kv) => kv.ready()?,
+        }
+        self.blobs.ready()
+    }
+}
+
+#[async_trait]
+impl KVTable for Table {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Self::Redb(table) => table.get(key).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.get(key).await,
+        }
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        match self {
+            Self::Redb(table) => table.put(key, value).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.put(key, value).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        match self {
+            Self::Redb(table) => table.delete(key).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.delete(key).await,
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        match self {
+            Self::Redb(table) => table.list(prefix).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.list(prefix).await,
+        }
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page, Error> {
+        match self {
+            Self::Redb(table) => table.list_page(prefix, cursor, limit).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.list_page(prefix, cursor, limit).await,
+        }
+    }
+
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error> {
+        match self {
+            Self::Redb(table) => table.get_with_metadata(key).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.get_with_metadata(key).await,
+        }
+    }
+
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Redb(table) => table.put_with_options(key, value, options).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(table) => table.put_with_options(key, value, options).await,
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use async_trait::async_trait;
+use pws_core::error::Error;
+use pws_core::types::{Entry, KVTable, Page, PutOptions};
+use redb::{
+    Database, Durability, ReadOnlyTable, ReadTransaction, ReadableDatabase, ReadableTable,
+    TableDefinition, TableError, TableHandle, WriteTransaction,
+};
+use std::fs;
+use std::io;
+use std::ops::Bound;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::{decode_options, now, storage_err};
+use crate::metrics::METRICS;
+
+/// 元数据与过期时间存放在 `<表名>#meta` 中, 值为 [`PutOptions`] 的 JSON
+const META_SUFFIX: &str = "#meta";
+
+type Bytes<'a> = TableDefinition<'a, &'static str, Vec<u8>>;
+
+fn open_read(
+    txn: &ReadTransaction,
+    name: &str,
+) -> Result<Option<ReadOnlyTable<&'static str, Vec<u8>>>, Error> {
+    match txn.open_table(Bytes::new(name)) {
+        Ok(t) => Ok(Some(t)),
+        Err(TableError::TableDoesNotExist(_)) => Ok(None),
+        Err(e) => Err(storage_err(e)),
+    }
+}
+
+fn has_table(txn: &WriteTransaction, name: &str) -> Result<bool, Error> {
+    Ok(txn
+        .list_tables()
+        .map_err(storage_err)?
+        .any(|handle| handle.name() == name))
+}
+
+fn read_options(
+    meta: Option<&ReadOnlyTable<&'static str, Vec<u8>>>,
+    key: &str,
+) -> Result<PutOptions, Error> {
+    let Some(meta) = meta else {
+        return Ok(PutOptions::default());
+    };
+    match meta.get(key).map_err(storage_err)? {
+        Some(raw) => decode_options(&raw.value()),
+        None => Ok(PutOptions::default()),
+    }
+}
+
+/// 数据库文件的独占锁, 持有期间其他进程无法打开该数据库
+pub struct FileLock {
+    _db: Database,
+}
+
+/// [`RedbKVStorage::lock`] 的错误
+#[derive(Debug)]
+pub enum Locked {
+    InUse,
+    Storage(Error),
+}
+
+#[derive(Clone)]
+pub struct RedbKVTable {
+    db: Arc<Database>,
+    table_name: String,
+}
+
+impl RedbKVTable {
+    pub fn new(db: Arc<Database>, table_name: String) -> Self {
+        Self { db, table_name }
+    }
+
+    fn meta_name(&self) -> String {
+        format!("{}{}", self.table_name, META_SUFFIX)
+    }
+
+    fn get_inner(&self, key: &str) -> Result<Option<Entry>, Error> {
+        let read_txn = self.db.begin_read().map_err(storage_err)?;
+        let Some(table) = open_read(&read_txn, &self.table_name)? else {
+            return Ok(None);
+        };
+        let Some(value) = table.get(key).map_err(storage_err)? else {
+            return Ok(None);
+        };
+        let meta = open_read(&read_txn, &self.meta_name())?;
+        let options = read_options(meta.as_ref(), key)?;
+        if options.is_expired(now()) {
+            return Ok(None);
+        }
+        Ok(Some(Entry::new(value.value(), options)))
+    }
+
+    fn put_inner(&self, key: &str, value: &[u8], options: &PutOptions) -> Result<(), Error> {
+        let meta_name = self.meta_name();
+        let write_txn = self.db.begin_write().map_err(storage_err)?;
+        {
+            let mut table = write_txn
+                .open_table(Bytes::new(&self.table_name))
+                .map_err(storage_err)?;
+            table.insert(key, value.to_vec()).map_err(storage_err)?;
+            if *options != PutOptions::default() {
+                let raw = serde_json::to_vec(options).map_err(storage_err)?;
+                let mut meta = write_txn
+                    .open_table(Bytes::new(&meta_name))
+                    .map_err(storage_err)?;
+                meta.insert(key, raw).map_err(storage_err)?;
+            } else if has_table(&write_txn, &meta_name)? {
+                let mut meta = write_txn
+                    .open_table(Bytes::new(&meta_name))
+                    .map_err(storage_err)?;
+                meta.remove(key).map_err(storage_err)?;
+            }
+        }
+        write_txn.commit().map_err(storage_err)
+    }
+
+    fn delete_inner(&self, key: &str) -> Result<(), Error> {
+        let meta_name = self.meta_name();
+        let write_txn = self.db.begin_write().map_err(storage_err)?;
+        {
+            let mut table = write_txn
+                .open_table(Bytes::new(&self.table_name))
+                .map_err(storage_err)?;
+            table.remove(key).map_err(storage_err)?;
+            if has_table(&write_txn, &meta_name)? {
+                let mut meta = write_txn
+                    .open_table(Bytes::new(&meta_name))
+                    .map_err(storage_err)?;
+                meta.remove(key).map_err(storage_err)?;
+            }
+        }
+        write_txn.commit().map_err(storage_err)
+    }
+
+    /// 列出 `prefix` 下排在 `after` 之后的未过期键, 最多 `limit` 个
+    fn list_inner(
+        &self,
+        prefix: &str,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, Error> {
+        let read_txn = self.db.begin_read().map_err(storage_err)?;
+        let Some(table) = open_read(&read_txn, &self.table_name)? else {
+            return Ok(Vec::new());
+        };
+        let meta = open_read(&read_txn, &self.meta_name())?;
+        let start = match after {
+            Some(after) if after >= prefix => Bound::Excluded(after),
+            _ => Bound::Included(prefix),
+        };
+        let now = now();
+        let mut keys = Vec::new();
+        for entry in table
+            .range::<&str>((start, Bound::Unbounded))
+            .map_err(storage_err)?
+        {
+            let (key, _) = entry.map_err(storage_err)?;
+            let key = key.value();
+            if !key.starts_with(prefix) || keys.len() == limit {
+                break;
+            }
+            if !read_options(meta.as_ref(), key)?.is_expired(now) {
+                keys.push(key.to_owned());
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[derive(Clone)]
+pub struct RedbKVStorage {
+    db: Arc<Database>,
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl RedbKVStorage {
+    pub fn new(path: String) -> Result<Self, redb::Error> {
+        let db = Arc::new(Database::create(path)?);
+        Ok(Self { db })
+    }
+
+    /// 以 Immediate 提交一个空事务, 确保此前的提交全部落盘
+    pub fn flush(&self) -> Result<(), Error> {
+        let mut txn = self.db.begin_write().map_err(storage_err)?;
+        txn.set_durability(Durability::Immediate)
+            .map_err(storage_err)?;
+        txn.commit().map_err(storage_err)
+    }
+
+    pub fn open_table(&self, table: &str) -> Result<RedbKVTable, Error> {
+        Ok(RedbKVTable::new(self.db.clone(), table.to_string()))
+    }
+
+    /// 在单个读事务内把全部表复制到 `dest`, 得到一致的快照且不阻塞写入.
+    /// 先写入临时文件再改名, 返回复制的记录数.
+    pub fn snapshot(&self, dest: &Path) -> Result<u64, Error> {
+        let tmp = dest.with_extension("tmp");
+        remove_if_exists(&tmp).map_err(storage_err)?;
+        let result = self.copy_to(&tmp).and_then(|records| {
+            fs::rename(&tmp, dest)
+                .map(|()| records)
+                .map_err(storage_err)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn copy_to(&self, path: &Path) -> Result<u64, Error> {
+        let read_txn = self.db.begin_read().map_err(storage_err)?;
+        let target = Database::create(path).map_err(storage_err)?;
+        let write_txn = target.begin_write().map_err(storage_err)?;
+        let mut records = 0;
+        for handle in read_txn.list_tables().map_err(storage_err)? {
+            let definition: TableDefinition<&str, Vec<u8>> = TableDefinition::new(handle.name());
+            let source = read_txn.open_table(definition).map_err(storage_err)?;
+            let mut dest = write_txn.open_table(definition).map_err(storage_err)?;
+            for entry in source.iter().map_err(storage_err)? {
+                let (key, value) = entry.map_err(storage_err)?;
+                dest.insert(key.value(), value.value())
+                    .map_err(storage_err)?;
+                records += 1;
+            }
+        }
+        write_txn.commit().map_err(storage_err)?;
+        Ok(records)
+    }
+
+    /// 打开快照并检查完整性, 再逐条读取全部表. 需要修复的文件视为损坏.
+    /// 返回打开的快照与记录数.
+    pub fn verify(path: &Path) -> Result<(Self, u64), Error> {
+        // redb 打开截断的文件时可能直接 panic, 这里转为错误. 不替换全局的 panic hook,
+        // 以免影响同一进程中的其他线程
+        let opened = panic::catch_unwind(|| {
+            let mut db = Database::open(path)?;
+            let clean = db.check_integrity()?;
+            Ok::<_, redb::DatabaseError>((db, clean))
+        });
+        let db = match opened {
+            Ok(Ok((db, true))) => db,
+            Ok(Ok((_, false))) => return Err(storage_err("integrity check failed")),
+            Ok(Err(e)) => return Err(storage_err(e)),
+            Err(_) => return Err(storage_err("damaged database file")),
+        };
+        let mut records = 0;
+        {
+            let read_txn = db.begin_read().map_err(storage_err)?;
+            for handle in read_txn.list_tables().map_err(storage_err)? {
+                let definition: TableDefinition<&str, Vec<u8>> =
+                    TableDefinition::new(handle.name());
+                let table = read_txn.open_table(definition).map_err(storage_err)?;
+                for entry in table.iter().map_err(storage_err)? {
+                    entry.map_err(storage_err)?;
+                    records += 1;
+                }
+            }
+        }
+        Ok((Self { db: Arc::new(db) }, records))
+    }
+
+    /// 打开 `path` 并持有其文件锁, 文件不存在时返回 `None`.
+    /// 已被其他进程或本进程的其他实例打开时返回 [`Locked::InUse`].
+    pub fn lock(path: &Path) -> Result<Option<FileLock>, Locked> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        match Database::open(path) {
+            Ok(db) => Ok(Some(FileLock { _db: db })),
+            Err(redb::DatabaseError::DatabaseAlreadyOpen) => Err(Locked::InUse),
+            Err(e) => Err(Locked::Storage(storage_err(e))),
+        }
+    }
+
+    /// 用已校验的 `staged` 替换 `live`, 原文件改名为 `<live>.<now>.bak` 保留.
+    /// 改名期间持有 [`RedbKVStorage::lock`] 得到的 `lock`, 完成后释放.
+    pub fn replace(
+        live: &Path,
+        staged: &Path,
+        now: u64,
+        lock: Option<FileLock>,
+    ) -> Result<Option<PathBuf>, Error> {
+        let backup = if live.exists() {
+            let backup = PathBuf::from(format!("{}.{}.bak", live.display(), now));
+            fs::rename(live, &backup).map_err(storage_err)?;
+            Some(backup)
+        } else {
+            None
+        };
+        fs::rename(staged, live).map_err(storage_err)?;
+        drop(lock);
+        Ok(backup)
+    }
+
+    /// 删除在 `now` 之前过期的记录及其元数据, 返回删除的数量
+    pub fn sweep_expired(&self, now: u64) -> Result<u64, Error> {
+        let write_txn = self.db.begin_write().map_err(storage_err)?;
+        let meta_tables: Vec<String> = write_txn
+            .list_tables()
+            .map_err(storage_err)?
+            .map(|handle| handle.name().to_owned())
+            .filter(|name| name.ends_with(META_SUFFIX))
+            .collect();
+        let mut swept = 0;
+        for meta_name in meta_tables {
+            let data_name = &meta_name[..meta_name.len() - META_SUFFIX.len()];
+            let mut meta = write_txn
+                .open_table(Bytes::new(&meta_name))
+                .map_err(storage_err)?;
+            let mut expired = Vec::new();
+            for entry in meta.iter().map_err(storage_err)? {
+                let (key, raw) = entry.map_err(storage_err)?;
+                if decode_options(&raw.value())?.is_expired(now) {
+                    expired.push(key.value().to_owned());
+                }
+            }
+            let mut data = write_txn
+                .open_table(Bytes::new(data_name))
+                .map_err(storage_err)?;
+            for key in &expired {
+                meta.remove(key.as_str()).map_err(storage_err)?;
+                data.remove(key.as_str()).map_err(storage_err)?;
+            }
+            swept += expired.len() as u64;
+        }
+        write_txn.commit().map_err(storage_err)?;
+        Ok(swept)
+    }
+
+    /// 就绪检查, 确认存储当前可读写
+    pub fn ready(&self) -> Result<(), Error> {
+        drop(self.db.begin_read().map_err(storage_err)?);
+        self.db
+            .begin_write()
+            .map_err(storage_err)?
+            .abort()
+            .map_err(storage_err)
+    }
+}
+
+#[async_trait]
+impl KVTable for RedbKVTable {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let start = Instant::now();
+        let result = self.get_inner(key);
+        METRICS.observe_kv(&self.table_name, "get", start);
+        result.map(|entry| entry.map(|entry| entry.value))
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.put_inner(key, value, &PutOptions::default());
+        METRICS.observe_kv(&self.table_name, "put", start);
+        result
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.delete_inner(key);
+        METRICS.observe_kv(&self.table_name, "delete", start);
+        result
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let start = Instant::now();
+        let result = self.list_inner(prefix, None, usize::MAX);
+        METRICS.observe_kv(&self.table_name, "list", start);
+        result
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page, Error> {
+        let start = Instant::now();
+        let limit = limit.max(1);
+        let result = self
+            .list_inner(prefix, cursor, limit.saturating_add(1))
+            .map(|keys| Page::from_lookahead(keys, limit));
+        METRICS.observe_kv(&self.table_name, "list", start);
+        result
+    }
+
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error> {
+        let start = Instant::now();
+        let result = self.get_inner(key);
+        METRICS.observe_kv(&self.table_name, "get", start);
+        result
+    }
+
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.put_inner(key, value, options);
+        METRICS.observe_kv(&self.table_name, "put", start);
+        result
+    }
+}
@@ -0,0 +1,308 @@
+use async_trait::async_trait;
+use pws_core::error::Error;
+use pws_core::types::{Entry, KVTable, Page, PutOptions};
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::{decode_options, now, storage_err};
+use crate::metrics::METRICS;
+
+type Shared = Arc<Mutex<Connection>>;
+
+fn lock(conn: &Shared) -> Result<MutexGuard<'_, Connection>, Error> {
+    conn.lock().map_err(storage_err)
+}
+
+/// 每个逻辑表对应一张同名的 SQLite 表
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// 元数据与过期时间存放在 `<表名>#meta` 中, `options` 为 [`PutOptions`] 的 JSON
+const META_SUFFIX: &str = "#meta";
+
+#[derive(Clone)]
+pub struct SqliteKVTable {
+    conn: Shared,
+    table_name: String,
+    ident: String,
+    meta_ident: String,
+}
+
+impl SqliteKVTable {
+    fn get_inner(&self, key: &str) -> Result<Option<Entry>, Error> {
+        let conn = lock(&self.conn)?;
+        let row: Option<(Vec<u8>, Option<String>)> = conn
+            .query_row(
+                &format!(
+                    "SELECT t.value, m.options FROM {} t LEFT JOIN {} m ON m.key = t.key \
+                     WHERE t.key = ?1 AND (m.expires_at IS NULL OR m.expires_at > ?2)",
+                    self.ident, self.meta_ident
+                ),
+                params![key, now() as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(storage_err)?;
+        let Some((value, options)) = row else {
+            return Ok(None);
+        };
+        let options = match options {
+            Some(raw) => decode_options(raw.as_bytes())?,
+            None => PutOptions::default(),
+        };
+        Ok(Some(Entry::new(value, options)))
+    }
+
+    fn put_inner(&self, key: &str, value: &[u8], options: &PutOptions) -> Result<(), Error> {
+        let mut conn = lock(&self.conn)?;
+        let txn = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(storage_err)?;
+        txn.execute(
+            &format!(
+                "INSERT INTO {} (key, value) VALUES (?1, ?2) \
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                self.ident
+            ),
+            params![key, value],
+        )
+        .map_err(storage_err)?;
+        if *options == PutOptions::default() {
+            txn.execute(
+                &format!("DELETE FROM {} WHERE key = ?1", self.meta_ident),
+                params![key],
+            )
+            .map_err(storage_err)?;
+        } else {
+            let raw = serde_json::to_string(options).map_err(storage_err)?;
+            txn.execute(
+                &format!(
+                    "INSERT INTO {} (key, options, expires_at) VALUES (?1, ?2, ?3) \
+                     ON CONFLICT(key) DO UPDATE SET options = excluded.options, \
+                     expires_at = excluded.expires_at",
+                    self.meta_ident
+                ),
+                params![key, raw, options.expires_at.map(|at| at as i64)],
+            )
+            .map_err(storage_err)?;
+        }
+        txn.commit().map_err(storage_err)
+    }
+
+    fn delete_inner(&self, key: &str) -> Result<(), Error> {
+        let mut conn = lock(&self.conn)?;
+        let txn = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(storage_err)?;
+        for ident in [&self.ident, &self.meta_ident] {
+            txn.execute(
+                &format!("DELETE FROM {} WHERE key = ?1", ident),
+                params![key],
+            )
+            .map_err(storage_err)?;
+        }
+        txn.commit().map_err(storage_err)
+    }
+
+    /// 列出 `prefix` 下排在 `after` 之后的未过期键, 最多 `limit` 个, 为 `None` 时不限
+    fn list_inner(
+        &self,
+        prefix: &str,
+        after: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, Error> {
+        let conn = lock(&self.conn)?;
+        // 与 redb 一致按字节序扫描, 避免 LIKE 的通配符转义
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "SELECT t.key FROM {} t LEFT JOIN {} m ON m.key = t.key \
+                 WHERE t.key >= ?1 AND (?3 IS NULL OR t.key > ?3) \
+                 AND (m.expires_at IS NULL OR m.expires_at > ?2) ORDER BY t.key LIMIT ?4",
+                self.ident, self.meta_ident
+            ))
+            .map_err(storage_err)?;
+        // 负数的 LIMIT 表示不限
+        let limit = limit.map_or(-1, |limit| i64::try_from(limit).unwrap_or(-1));
+        let mut rows = stmt
+            .query(params![prefix, now() as i64, after, limit])
+            .map_err(storage_err)?;
+        let mut keys = Vec::new();
+        while let Some(row) = rows.next().map_err(storage_err)? {
+            let key: String = row.get(0).map_err(storage_err)?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+}
+
+pub struct SqliteKVStorage {
+    conn: Shared,
+    /// 本进程中已确认存在的逻辑表, 每张表只执行一次建表语句
+    created: Mutex<HashSet<String>>,
+}
+
+impl SqliteKVStorage {
+    pub fn new(path: &str) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            created: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// 将 WAL 中的内容合并回主库文件
+    pub fn flush(&self) -> Result<(), Error> {
+        let conn = lock(&self.conn)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(storage_err)
+    }
+
+    pub fn open_table(&self, table: &str) -> Result<SqliteKVTable, Error> {
+        let ident = quote(table);
+        let meta_ident = quote(&format!("{}{}", table, META_SUFFIX));
+        let mut created = self.created.lock().map_err(storage_err)?;
+        if !created.contains(table) {
+            lock(&self.conn)?
+                .execute_batch(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY NOT NULL, value BLOB NOT NULL) WITHOUT ROWID;
+                     CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY NOT NULL, options TEXT NOT NULL, expires_at INTEGER) WITHOUT ROWID;",
+                    ident, meta_ident
+                ))
+                .map_err(storage_err)?;
+            created.insert(table.to_owned());
+        }
+        Ok(SqliteKVTable {
+            conn: self.conn.clone(),
+            table_name: table.to_string(),
+            ident,
+            meta_ident,
+        })
+    }
+
+    /// 删除在 `now` 之前过期的记录及其元数据, 返回删除的数量
+    pub fn sweep_expired(&self, now: u64) -> Result<u64, Error> {
+        let mut conn = lock(&self.conn)?;
+        let txn = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(storage_err)?;
+        let meta_tables: Vec<String> = {
+            let mut stmt = txn
+                .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+                .map_err(storage_err)?;
+            let names = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(storage_err)?;
+            names
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(storage_err)?
+                .into_iter()
+                .filter(|name| name.ends_with(META_SUFFIX))
+                .collect()
+        };
+        let mut swept = 0;
+        for meta_name in meta_tables {
+            let data = quote(&meta_name[..meta_name.len() - META_SUFFIX.len()]);
+            let meta = quote(&meta_name);
+            txn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE key IN \
+                     (SELECT key FROM {} WHERE expires_at <= ?1)",
+                    data, meta
+                ),
+                params![now as i64],
+            )
+            .map_err(storage_err)?;
+            swept += txn
+                .execute(
+                    &format!("DELETE FROM {} WHERE expires_at <= ?1", meta),
+                    params![now as i64],
+                )
+                .map_err(storage_err)? as u64;
+        }
+        txn.commit().map_err(storage_err)?;
+        Ok(swept)
+    }
+
+    /// 就绪检查, 确认存储当前可读写
+    pub fn ready(&self) -> Result<(), Error> {
+        let mut conn = lock(&self.conn)?;
+        let txn = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(storage_err)?;
+        txn.rollback().map_err(storage_err)
+    }
+}
+
+#[async_trait]
+impl KVTable for SqliteKVTable {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let start = Instant::now();
+        let result = self.get_inner(key);
+        METRICS.observe_kv(&self.table_name, "get", start);
+        result.map(|entry| entry.map(|entry| entry.value))
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.put_inner(key, value, &PutOptions::default());
+        METRICS.observe_kv(&self.table_name, "put", start);
+        result
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.delete_inner(key);
+        METRICS.observe_kv(&self.table_name, "delete", start);
+        result
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let start = Instant::now();
+        let result = self.list_inner(prefix, None, None);
+        METRICS.observe_kv(&self.table_name, "list", start);
+        result
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Page, Error> {
+        let start = Instant::now();
+        let limit = limit.max(1);
+        let result = self
+            .list_inner(prefix, cursor, Some(limit.saturating_add(1)))
+            .map(|keys| Page::from_lookahead(keys, limit));
+        METRICS.observe_kv(&self.table_name, "list", start);
+        result
+    }
+
+    async fn get_with_metadata(&self, key: &str) -> Result<Option<Entry>, Error> {
+        let start = Instant::now();
+        let result = self.get_inner(key);
+        METRICS.observe_kv(&self.table_name, "get", start);
+        result
+    }
+
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self.put_inner(key, value, options);
+        METRICS.observe_kv(&self.table_name, "put", start);
+        result
+    }
+}
@@ -0,0 +1,26 @@
+//! 服务端的各个模块, 二进制入口位于 `main.rs`, 以库的形式导出以便集成测试使用
+
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod gc;
+pub mod kv;
+pub mod log;
+pub mod metrics;
+#[cfg(unix)]
+pub mod reload;
+pub mod retention;
+pub mod shutdown;
+pub mod snapshot;
+pub mod tls;
+pub mod types;
+pub mod utils;
+
+use std::process;
+
+use pws_core::i18n::{Lang, Msg};
+
+pub fn exit_with(msg: Msg, lang: Lang) -> ! {
+    eprintln!("{}", msg.text(lang));
+    process::exit(1)
+}
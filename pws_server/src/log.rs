@@ -0,0 +1,27 @@
+use pws_core::types::LogLevel;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry, reload};
+
+use crate::types::LogFormat;
+
+/// 用于在运行时调整日志等级, 输出格式则只能在启动时确定
+pub type LevelHandle = reload::Handle<LevelFilter, Registry>;
+
+pub fn init(level: LogLevel, format: LogFormat) -> LevelHandle {
+    let (filter, handle) = reload::Layer::new(level.level_filter());
+
+    let fmt = tracing_subscriber::fmt::layer().with_target(false);
+    let fmt = match format {
+        LogFormat::Human => fmt.boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry().with(filter).with(fmt).init();
+    handle
+}
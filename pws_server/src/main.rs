@@ -1,53 +1,221 @@
-mod kv;
-mod types;
-mod utils;
-
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::signal;
+use tokio::net::TcpListener;
 
-use pws_core::routes::router;
+use axum::Router;
+use axum::extract::DefaultBodyLimit;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum_server::Handle;
+use clap::Parser;
+use pws_core::i18n::{Lang, Msg};
+use pws_core::routes::{routes, with_request_id};
 use pws_core::types::AppState;
-use std::fs;
+use tower_http::timeout::TimeoutLayer;
+
+use pws_server::cli::{Cli, Command};
+use pws_server::config::Config;
+use pws_server::kv::Storage;
+#[cfg(unix)]
+use pws_server::reload;
+use pws_server::shutdown::Shutdown;
+use pws_server::utils::ServerUtils;
+use pws_server::{commands, exit_with, gc, log, metrics, retention, snapshot, tls};
 
-use crate::kv::RedbKVStorage;
-use crate::utils::{ServerUtils, handler_404};
+async fn bind(addr: &str, lang: Lang) -> TcpListener {
+    let listener = TcpListener::bind(addr).await.unwrap_or_else(|e| {
+        exit_with(
+            Msg::BindFailed {
+                addr,
+                error: &e.to_string(),
+            },
+            lang,
+        )
+    });
+    tracing::info!("{}", Msg::Listening(addr).text(lang));
+    listener
+}
+
+fn load_config(path: Option<&str>) -> Config {
+    Config::load(path).unwrap_or_else(|e| exit_with(e.msg(), Lang::default()))
+}
 
 #[tokio::main]
 async fn main() {
-    let config_data: types::Config =
-        serde_json::from_str(&fs::read_to_string("./config.json").unwrap()).unwrap();
+    let cli = Cli::parse();
+    // `decode` 不需要配置文件, 其余子命令按需加载
+    let config = || load_config(cli.config.as_deref());
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config(), cli.config.clone()).await,
+        Command::ImportSave {
+            openid,
+            zip,
+            nickname,
+            file_object_id,
+        } => {
+            commands::import_save(
+                &config(),
+                &openid,
+                &zip,
+                nickname.as_deref(),
+                file_object_id.as_deref(),
+            )
+            .await
+        }
+        Command::Export { openid, output } => {
+            commands::export(&config(), &openid, output.as_deref()).await
+        }
+        Command::Decode { zip } => commands::decode(&zip),
+        Command::Sign { file } => commands::sign_file(&config(), &file),
+        Command::Users => commands::users(&config()).await,
+        Command::Gc => commands::gc(&config()).await,
+        Command::Retention { dry_run } => commands::retention(&config(), dry_run).await,
+        Command::RebuildIndex => commands::rebuild_index(&config()).await,
+        Command::Dump { output } => commands::dump(&config(), &output).await,
+        Command::Load { input } => commands::load(&config(), &input).await,
+        Command::Migrate { target } => commands::migrate(&config(), &target).await,
+        Command::Restore {
+            snapshot,
+            check,
+            force,
+        } => commands::restore(&config(), &snapshot, check, force).await,
+    }
+}
 
-    let utils = ServerUtils::new(
-        config_data.file_url_template.clone(),
-        config_data.sign_key.as_bytes().to_vec(),
-        config_data.log_level,
-    );
+async fn serve(config_data: Config, config_path: Option<String>) {
+    let lang = config_data.log_lang;
+    let level = log::init(config_data.log_level, config_data.log_format);
 
-    let kv = RedbKVStorage::new(config_data.kv_storage_path.clone()).unwrap();
+    let utils = ServerUtils::new(&config_data)
+        .unwrap_or_else(|e| exit_with(Msg::HttpClientFailed(&e.to_string()), lang));
+
+    let kv = Storage::open(&config_data.storage())
+        .unwrap_or_else(|e| exit_with(Msg::StorageOpenFailed(&e.to_string()), lang));
 
     let state = Arc::new(AppState { utils, kv });
+    let shutdown = Shutdown::listen(lang);
 
-    let app = router(state).fallback(handler_404);
+    #[cfg(unix)]
+    reload::spawn(
+        config_path,
+        config_data.clone(),
+        Arc::downgrade(&state),
+        level,
+    );
+    #[cfg(not(unix))]
+    let _ = (config_path, level);
+    gc::spawn(&config_data.blobs, Arc::downgrade(&state));
+    retention::spawn(&config_data.retention, Arc::downgrade(&state));
+    if let Some(snapshots) = &config_data.snapshots {
+        snapshot::spawn(snapshots, Arc::downgrade(&state));
+    }
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
-        .await
-        .expect("Failed to bind TCP listener");
+    let mut app = routes(state.clone());
 
-    let server = axum::serve(listener, app);
+    match config_data.metrics_addr.clone() {
+        Some(addr) => {
+            let listener = bind(&addr, lang).await;
+            let admin = Router::new().route("/metrics", get(metrics::handler));
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                let server = axum::serve(listener, admin).with_graceful_shutdown(shutdown.wait());
+                if let Err(err) = server.await {
+                    tracing::error!("{}", Msg::ServerError(&err.to_string()).text(lang));
+                }
+            });
+        }
+        None => app = app.route("/metrics", get(metrics::handler)),
+    }
 
-    let shutdown_signal = async {
-        signal::ctrl_c()
-            .await
-            .expect("Failed to install Ctrl+C handler");
-        println!("Shutting down...");
+    // 超时与请求体上限放在 request_id 之内, 以便它们的错误响应也渲染为 JSON
+    let app = with_request_id(
+        app.layer(DefaultBodyLimit::max(config_data.server.body_limit))
+            .layer(TimeoutLayer::with_status_code(
+                StatusCode::REQUEST_TIMEOUT,
+                config_data.server.request_timeout(),
+            )),
+        state.clone(),
+    );
+
+    let rustls = match &config_data.tls {
+        Some(tls) => {
+            let rustls = tls::load(tls)
+                .await
+                .unwrap_or_else(|e| exit_with(Msg::TlsLoadFailed(&e.to_string()), lang));
+            tls::watch(rustls.clone(), tls.clone(), lang);
+            if let Some(port) = tls.redirect_port {
+                let addr = SocketAddr::new(config_data.bind_addr().ip(), port);
+                let listener = bind(&addr.to_string(), lang).await;
+                let host = tls
+                    .public_host
+                    .as_deref()
+                    .expect("validated in Config::load");
+                let redirect = tls::redirect_router(host, config_data.server.port);
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move {
+                    let server =
+                        axum::serve(listener, redirect).with_graceful_shutdown(shutdown.wait());
+                    if let Err(err) = server.await {
+                        tracing::error!("{}", Msg::ServerError(&err.to_string()).text(lang));
+                    }
+                });
+            }
+            Some(rustls)
+        }
+        None => None,
+    };
+
+    let listener = bind(&config_data.bind_addr().to_string(), lang).await;
+
+    let server = {
+        let shutdown = shutdown.clone();
+        let drain = config_data.server.shutdown_timeout();
+        async move {
+            match rustls {
+                Some(rustls) => {
+                    let handle = Handle::new();
+                    let graceful = handle.clone();
+                    tokio::spawn(async move {
+                        shutdown.wait().await;
+                        graceful.graceful_shutdown(Some(drain));
+                    });
+                    axum_server::from_tcp_rustls(listener.into_std()?, rustls)?
+                        .handle(handle)
+                        .serve(app.into_make_service())
+                        .await
+                }
+                None => {
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(shutdown.wait())
+                        .await
+                }
+            }
+        }
+    };
+
+    let drain = config_data.server.shutdown_timeout();
+    let deadline = async {
+        shutdown.clone().wait().await;
+        tokio::time::sleep(drain).await;
     };
 
     tokio::select! {
         result = server => {
             if let Err(err) = result {
-                eprintln!("Server error: {}", err);
+                tracing::error!("{}", Msg::ServerError(&err.to_string()).text(lang));
             }
         },
-        _ = shutdown_signal => {},
+        _ = deadline => {
+            tracing::warn!("{}", Msg::DrainTimedOut(drain.as_secs()).text(lang));
+        },
+    }
+
+    if let Err(e) = state.kv.flush() {
+        tracing::error!("{}", e.message(lang));
+    }
+    // 释放最后一个引用以正常关闭数据库, 否则下次启动时 redb 可能需要修复
+    if let Ok(state) = Arc::try_unwrap(state) {
+        drop(state);
     }
+    tracing::info!("{}", Msg::ShutdownComplete.text(lang));
 }
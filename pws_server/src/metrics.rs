@@ -0,0 +1,133 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use pws_core::metrics::Metric;
+
+pub struct Metrics {
+    registry: Registry,
+    webhooks: IntCounterVec,
+    sign_failures: IntCounterVec,
+    parse_failures: IntCounterVec,
+    info_requests: IntCounterVec,
+    file_fetch_seconds: HistogramVec,
+    file_fetch_errors: IntCounter,
+    kv_seconds: HistogramVec,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("pws".to_owned()), None).expect("invalid metrics prefix");
+
+        let webhooks = IntCounterVec::new(
+            Opts::new("webhooks_total", "Webhooks received"),
+            &["type", "action", "outcome"],
+        )
+        .unwrap();
+        let sign_failures = IntCounterVec::new(
+            Opts::new("sign_failures_total", "Rejected X-Sign checks"),
+            &["reason"],
+        )
+        .unwrap();
+        let parse_failures = IntCounterVec::new(
+            Opts::new("parse_failures_total", "Save fields that failed to decode"),
+            &["field"],
+        )
+        .unwrap();
+        let info_requests = IntCounterVec::new(
+            Opts::new("info_requests_total", "Info requests"),
+            &["route", "status"],
+        )
+        .unwrap();
+        let file_fetch_seconds = HistogramVec::new(
+            HistogramOpts::new("file_fetch_duration_seconds", "Save file fetch latency"),
+            &["outcome"],
+        )
+        .unwrap();
+        let file_fetch_errors =
+            IntCounter::new("file_fetch_errors_total", "Failed save file fetches").unwrap();
+        let kv_seconds = HistogramVec::new(
+            HistogramOpts::new("kv_operation_duration_seconds", "KV operation latency")
+                .buckets(vec![0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0]),
+            &["table", "op"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(webhooks.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(sign_failures.clone()),
+            Box::new(parse_failures.clone()),
+            Box::new(info_requests.clone()),
+            Box::new(file_fetch_seconds.clone()),
+            Box::new(file_fetch_errors.clone()),
+            Box::new(kv_seconds.clone()),
+        ] {
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            webhooks,
+            sign_failures,
+            parse_failures,
+            info_requests,
+            file_fetch_seconds,
+            file_fetch_errors,
+            kv_seconds,
+        }
+    }
+
+    pub fn record(&self, metric: Metric<'_>) {
+        match metric {
+            Metric::Webhook {
+                r#type,
+                action,
+                outcome,
+            } => self
+                .webhooks
+                .with_label_values(&[r#type, action, outcome])
+                .inc(),
+            Metric::SignFailure { reason } => self.sign_failures.with_label_values(&[reason]).inc(),
+            Metric::ParseFailure { field } => self.parse_failures.with_label_values(&[field]).inc(),
+            Metric::InfoRequest { route, status } => self
+                .info_requests
+                .with_label_values(&[route, &status.to_string()])
+                .inc(),
+        }
+    }
+
+    pub fn observe_fetch(&self, start: Instant, ok: bool) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.file_fetch_seconds
+            .with_label_values(&[outcome])
+            .observe(start.elapsed().as_secs_f64());
+        if !ok {
+            self.file_fetch_errors.inc();
+        }
+    }
+
+    pub fn observe_kv(&self, table: &str, op: &str, start: Instant) {
+        self.kv_seconds
+            .with_label_values(&[table, op])
+            .observe(start.elapsed().as_secs_f64());
+    }
+
+    fn render(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("failed to encode metrics");
+        buf
+    }
+}
+
+pub async fn handler() -> impl IntoResponse {
+    ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], METRICS.render())
+}
@@ -0,0 +1,63 @@
+use std::sync::Weak;
+
+use pws_core::i18n::Msg;
+use pws_core::types::AppState;
+use tokio::signal::unix::{SignalKind, signal};
+
+use crate::config::Config;
+use crate::kv::Storage;
+use crate::log::LevelHandle;
+use crate::utils::ServerUtils;
+
+type State = Weak<AppState<ServerUtils, Storage>>;
+
+/// 收到 SIGHUP 时重新读取配置; 任一步失败都保留旧配置, 监听器不受影响.
+/// 只持有弱引用, 不妨碍关闭时释放存储.
+pub fn spawn(path: Option<String>, mut current: Config, state: State, level: LevelHandle) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            let msg = Msg::SignalHandlerFailed(&e.to_string());
+            tracing::error!("{}", msg.text(current.log_lang));
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            let Some(state) = state.upgrade() else { break };
+            let lang = current.log_lang;
+            let failed = |error: &str| {
+                tracing::error!("{}", Msg::ConfigReloadFailed(error).text(lang));
+            };
+
+            let next = match Config::load(path.as_deref()) {
+                Ok(next) => next,
+                Err(e) => {
+                    failed(&e.msg().text(lang));
+                    continue;
+                }
+            };
+            if let Err(e) = state.utils.reload(&next) {
+                failed(&Msg::HttpClientFailed(&e.to_string()).text(lang));
+                continue;
+            }
+            let (changes, restart) = current.diff(&next);
+            let lang = next.log_lang;
+            if changes.is_empty() {
+                tracing::info!("{}", Msg::ConfigUnchanged.text(lang));
+            } else {
+                tracing::info!("{}", Msg::ConfigReloaded(&changes.join(", ")).text(lang));
+            }
+            if !restart.is_empty() {
+                let msg = Msg::ConfigRestartRequired(&restart.join(", "));
+                tracing::warn!("{}", msg.text(lang));
+            }
+            // 最后才切换日志等级, 保证上面的变化记录按旧等级输出
+            if let Err(e) = level.reload(next.log_level.level_filter()) {
+                tracing::error!("{}", Msg::ConfigReloadFailed(&e.to_string()).text(lang));
+            }
+            current = next;
+        }
+    });
+}
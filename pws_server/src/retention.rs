@@ -0,0 +1,32 @@
+use std::sync::Weak;
+use std::time::Duration;
+
+use pws_core::retention;
+use pws_core::types::{AppState, AppUtils};
+
+use crate::config::RetentionConfig;
+use crate::kv::Storage;
+use crate::utils::ServerUtils;
+
+type State = Weak<AppState<ServerUtils, Storage>>;
+
+/// 定期执行数据保留策略, `dry_run` 时只记录将被清理的内容
+pub fn spawn(config: &RetentionConfig, state: State) {
+    let interval = Duration::from_secs(config.interval_secs);
+    let policy = config.policy();
+    let dry_run = config.dry_run;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(state) = state.upgrade() else { break };
+            let lang = state.utils.log_lang();
+            let now = state.utils.now();
+            match retention::enforce(&state.kv, &policy, now, dry_run).await {
+                Ok(report) => report.log(lang),
+                Err(e) => tracing::error!("{}", e.message(lang)),
+            }
+        }
+    });
+}
@@ -0,0 +1,51 @@
+use pws_core::i18n::{Lang, Msg};
+use tokio::signal;
+use tokio::sync::watch;
+
+use crate::exit_with;
+
+/// 关闭信号, 由各监听器与后台任务共享
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// 收到 SIGTERM 或 SIGINT 后触发
+    pub fn listen(lang: Lang) -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            wait_for_signal(lang).await;
+            tracing::info!("{}", Msg::ShuttingDown.text(lang));
+            let _ = tx.send(true);
+        });
+        Self(rx)
+    }
+
+    pub async fn wait(mut self) {
+        let _ = self.0.wait_for(|triggered| *triggered).await;
+    }
+}
+
+async fn wait_for_signal(lang: Lang) {
+    let ctrl_c = async {
+        if let Err(e) = signal::ctrl_c().await {
+            exit_with(Msg::SignalHandlerFailed(&e.to_string()), lang);
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => exit_with(Msg::SignalHandlerFailed(&e.to_string()), lang),
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Weak;
+use std::time::Duration;
+
+use pws_core::error::Error;
+use pws_core::i18n::Msg;
+use pws_core::store;
+use pws_core::types::{AppState, AppUtils, BlobStore, KVStorage};
+use tokio::time::MissedTickBehavior;
+
+use crate::config::{SnapshotConfig, StorageConfig};
+use crate::kv::{Backend, FileLock, FsBlobStore, Locked, RedbKVStorage, Storage};
+use crate::utils::ServerUtils;
+
+type State = Weak<AppState<ServerUtils, Storage>>;
+
+const PREFIX: &str = "pws-";
+const EXTENSION: &str = ".redb";
+const BLOBS_SUFFIX: &str = ".blobs";
+
+/// 一次快照的结果
+pub struct Taken {
+    pub path: PathBuf,
+    pub records: u64,
+    /// 链接到 [`blobs_dir`] 的存档文件数
+    pub blobs: usize,
+    pub pruned: usize,
+}
+
+fn storage_err(e: impl ToString) -> Error {
+    Error::Storage(e.to_string())
+}
+
+/// 快照引用的存档文件存放在快照旁的 `<快照>.blobs/` 中, 目录结构与 `blobs.path` 相同
+pub fn blobs_dir(snapshot: &Path) -> PathBuf {
+    PathBuf::from(format!("{}{}", snapshot.display(), BLOBS_SUFFIX))
+}
+
+async fn hashes(blobs: &FsBlobStore) -> Result<HashSet<String>, Error> {
+    Ok(blobs.list().await?.into_iter().map(|b| b.hash).collect())
+}
+
+/// 在 `config.path` 下创建以毫秒时间戳命名的快照, 把它引用的存档文件硬链接到 [`blobs_dir`],
+/// 并按 `config.keep` 删除最旧的快照. 复制数据库在阻塞线程中进行.
+pub async fn take(
+    kv: &RedbKVStorage,
+    blobs: &FsBlobStore,
+    config: &SnapshotConfig,
+    now: u64,
+) -> Result<Taken, Error> {
+    let dir = PathBuf::from(&config.path);
+    fs::create_dir_all(&dir).map_err(storage_err)?;
+    // 补零使文件名的字典序与时间顺序一致
+    let path = dir.join(format!("{}{:013}{}", PREFIX, now, EXTENSION));
+    let records = {
+        let (kv, path) = (kv.clone(), path.clone());
+        tokio::task::spawn_blocking(move || kv.snapshot(&path))
+            .await
+            .map_err(storage_err)??
+    };
+    let linked = link_blobs(&path, blobs).await?;
+    let pruned = prune(&dir, config.keep).map_err(storage_err)?;
+    Ok(Taken {
+        path,
+        records,
+        blobs: linked,
+        pruned,
+    })
+}
+
+/// 快照之后才被回收的存档文件会被跳过, 恢复时报告为缺失
+async fn link_blobs(snapshot: &Path, live: &FsBlobStore) -> Result<usize, Error> {
+    let kv = RedbKVStorage::new(snapshot.to_string_lossy().into_owned()).map_err(storage_err)?;
+    let saved = FsBlobStore::new(blobs_dir(snapshot)).map_err(storage_err)?;
+    let kv = Storage::new(Backend::Redb(kv), saved.clone());
+    let mut linked = 0;
+    for hash in store::referenced_blobs(&kv).await? {
+        if live.link_to(&hash, &saved)? {
+            linked += 1;
+        }
+    }
+    Ok(linked)
+}
+
+fn prune(dir: &Path, keep: usize) -> io::Result<usize> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.starts_with(PREFIX) && name.ends_with(EXTENSION) {
+            snapshots.push(name);
+        }
+    }
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for name in &snapshots[..excess] {
+        let path = dir.join(name);
+        fs::remove_file(&path)?;
+        match fs::remove_dir_all(blobs_dir(&path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(excess)
+}
+
+/// 把快照复制到数据库旁的临时文件, 校验与替换都在这份副本上进行
+fn stage(snapshot: &Path, live: &Path) -> io::Result<PathBuf> {
+    let staged = PathBuf::from(format!("{}.restore", live.display()));
+    fs::copy(snapshot, &staged)?;
+    fs::File::open(&staged)?.sync_all()?;
+    Ok(staged)
+}
+
+#[derive(Debug)]
+pub enum RestoreError {
+    Read { path: String, error: String },
+    InUse(String),
+    Storage(Error),
+}
+
+impl RestoreError {
+    pub fn msg(&self) -> Msg<'_> {
+        match self {
+            RestoreError::Read { path, error } => Msg::FileReadFailed { path, error },
+            RestoreError::InUse(path) => Msg::SnapshotDatabaseInUse(path),
+            RestoreError::Storage(e) => e.msg(),
+        }
+    }
+}
+
+/// 已暂存并校验的快照. 存在期间持有线上数据库的文件锁, 丢弃时删除暂存的副本.
+pub struct Restore {
+    live: PathBuf,
+    staged: PathBuf,
+    lock: Option<FileLock>,
+    blobs: FsBlobStore,
+    saved: Option<FsBlobStore>,
+    /// 线上已被回收, 但快照旁仍保留的存档文件
+    recoverable: Vec<String>,
+    pub records: u64,
+    /// 线上与快照旁都不存在的存档文件数
+    pub missing: usize,
+}
+
+impl Restore {
+    /// 锁定线上数据库, 再把快照复制到它旁边做完整性检查与全表读取,
+    /// 并确认快照引用的存档文件仍然存在. 数据库正被使用时失败.
+    pub async fn prepare(snapshot: &Path, storage: &StorageConfig) -> Result<Self, RestoreError> {
+        let live = PathBuf::from(&storage.kv_storage_path);
+        let lock = RedbKVStorage::lock(&live).map_err(|e| match e {
+            Locked::InUse => RestoreError::InUse(storage.kv_storage_path.clone()),
+            Locked::Storage(e) => RestoreError::Storage(e),
+        })?;
+        let blobs = FsBlobStore::new(&storage.blobs.path)
+            .map_err(|e| RestoreError::Storage(storage_err(e)))?;
+        let staged = stage(snapshot, &live).map_err(|e| RestoreError::Read {
+            path: snapshot.display().to_string(),
+            error: e.to_string(),
+        })?;
+        let mut restore = Self {
+            live,
+            staged,
+            lock,
+            blobs,
+            saved: None,
+            recoverable: Vec::new(),
+            records: 0,
+            missing: 0,
+        };
+        restore
+            .check(&blobs_dir(snapshot))
+            .await
+            .map_err(RestoreError::Storage)?;
+        Ok(restore)
+    }
+
+    async fn check(&mut self, saved_dir: &Path) -> Result<(), Error> {
+        let (kv, records) = RedbKVStorage::verify(&self.staged)?;
+        let kv = Storage::new(Backend::Redb(kv), self.blobs.clone());
+        let saved = if saved_dir.is_dir() {
+            Some(FsBlobStore::new(saved_dir).map_err(storage_err)?)
+        } else {
+            None
+        };
+        let live = hashes(&self.blobs).await?;
+        let kept = match &saved {
+            Some(saved) => hashes(saved).await?,
+            None => HashSet::new(),
+        };
+        for hash in store::referenced_blobs(&kv).await? {
+            if live.contains(&hash) {
+                continue;
+            }
+            if kept.contains(&hash) {
+                self.recoverable.push(hash);
+            } else {
+                self.missing += 1;
+            }
+        }
+        self.records = records;
+        self.saved = saved;
+        Ok(())
+    }
+
+    /// 从快照旁取回已被回收的存档文件, 再替换线上数据库.
+    /// 返回取回的文件数与原数据库的备份路径.
+    pub fn apply(&mut self, now: u64) -> Result<(usize, Option<PathBuf>), Error> {
+        let mut restored = 0;
+        if let Some(saved) = &self.saved {
+            for hash in &self.recoverable {
+                if saved.link_to(hash, &self.blobs)? {
+                    restored += 1;
+                }
+            }
+        }
+        let backup = RedbKVStorage::replace(&self.live, &self.staged, now, self.lock.take())?;
+        Ok((restored, backup))
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.staged);
+    }
+}
+
+/// 定期快照 redb 数据库. 复制在阻塞线程中进行, 只在复制期间持有数据库.
+pub fn spawn(config: &SnapshotConfig, state: State) {
+    let config = config.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(state) = state.upgrade() else { break };
+            let lang = state.utils.log_lang();
+            let now = state.utils.now();
+            let Some(kv) = state.kv.redb().cloned() else {
+                break;
+            };
+            let blobs = state.kv.blobs().clone();
+            drop(state);
+
+            match take(&kv, &blobs, &config, now).await {
+                Ok(taken) => {
+                    let path = taken.path.display().to_string();
+                    tracing::info!(
+                        "{}",
+                        Msg::SnapshotCreated {
+                            path: &path,
+                            records: taken.records,
+                            blobs: taken.blobs
+                        }
+                        .text(lang)
+                    );
+                    if taken.pruned > 0 {
+                        tracing::info!("{}", Msg::SnapshotsPruned(taken.pruned).text(lang));
+                    }
+                }
+                Err(e) => tracing::error!("{}", e.message(lang)),
+            }
+        }
+    });
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use axum::Router;
+use axum::extract::Request;
+use axum::response::{IntoResponse, Redirect};
+use axum_server::tls_rustls::RustlsConfig;
+use pws_core::i18n::{Lang, Msg};
+
+use crate::config::TlsConfig;
+
+pub async fn load(config: &TlsConfig) -> io::Result<RustlsConfig> {
+    // rustls 只启用了 ring, 这里显式安装以免依赖自动选择
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&config.cert_path, &config.key_path).await
+}
+
+fn modified(config: &TlsConfig) -> Option<(SystemTime, SystemTime)> {
+    let mtime = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((mtime(&config.cert_path)?, mtime(&config.key_path)?))
+}
+
+/// 定期检查证书与私钥的修改时间, 变化后重新加载; 加载失败时继续使用旧证书
+pub fn watch(rustls: RustlsConfig, config: TlsConfig, lang: Lang) {
+    tokio::spawn(async move {
+        let mut last = modified(&config);
+        let mut interval = tokio::time::interval(Duration::from_secs(config.reload_interval_secs));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let current = modified(&config);
+            if current.is_none() || current == last {
+                continue;
+            }
+            match rustls
+                .reload_from_pem_file(&config.cert_path, &config.key_path)
+                .await
+            {
+                Ok(()) => {
+                    last = current;
+                    tracing::info!("{}", Msg::TlsReloaded.text(lang));
+                }
+                Err(e) => {
+                    tracing::error!("{}", Msg::TlsLoadFailed(&e.to_string()).text(lang));
+                }
+            }
+        }
+    });
+}
+
+/// 明文端口上的所有请求都以 308 重定向到 `public_host` 的 HTTPS 端口.
+/// 不使用请求中的 `Host`, 以免被用作开放重定向.
+pub fn redirect_router(public_host: &str, https_port: u16) -> Router {
+    let origin = match https_port {
+        443 => format!("https://{}", public_host),
+        port => format!("https://{}:{}", public_host, port),
+    };
+    Router::new().fallback(move |req: Request| async move {
+        let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+        Redirect::permanent(&format!("{}{}", origin, path)).into_response()
+    })
+}
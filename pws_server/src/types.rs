@@ -1,10 +1,19 @@
-use pws_core::types::LogLevel;
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
-pub struct Config {
-    pub log_level: LogLevel,
-    pub kv_storage_path: String,
-    pub sign_key: String,
-    pub file_url_template: String,
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// 存储后端, `sqlite` 需要启用同名 feature
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KvBackend {
+    #[default]
+    Redb,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
 }
@@ -1,79 +1,139 @@
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use axum::response::IntoResponse;
-use base64::{Engine as _, engine::general_purpose::URL_SAFE};
-use blake2::{
-    Blake2sMac,
-    digest::{Mac, consts::U16},
-};
-use pws_core::types::{AppUtils, LogLevel};
-use reqwest::{Client, StatusCode};
-
-fn sign(key: &[u8], data: &[u8]) -> String {
-    let mut mac =
-        Blake2sMac::<U16>::new_with_salt_and_personal(key, &[], &[]).expect("invalid length");
-
-    mac.update(data);
-
-    let result = mac.finalize();
-    URL_SAFE.encode(result.into_bytes())
+use pws_core::error::Error;
+use pws_core::i18n::Lang;
+use pws_core::metrics::Metric;
+use pws_core::retention::RetentionPolicy;
+use pws_core::secret::Secret;
+use pws_core::sign::sign;
+use pws_core::types::AppUtils;
+use reqwest::{Client, Url};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::config::{Config, HttpClientConfig};
+use crate::metrics::METRICS;
+
+fn build_client(config: &HttpClientConfig) -> Result<Client, reqwest::Error> {
+    Client::builder()
+        .danger_accept_invalid_certs(config.accept_invalid_certs)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
 }
 
-pub struct ServerUtils {
+/// 可在 SIGHUP 时整体替换的部分
+struct Settings {
     file_url_template: String,
+    http_client: HttpClientConfig,
     client: Client,
-    sign_key: Vec<u8>,
-    log_level: LogLevel,
+    sign_key: Secret<Vec<u8>>,
+    admin_token: Option<Secret>,
+    log_lang: Lang,
 }
 
-impl ServerUtils {
-    pub fn new(file_url_template: String, sign_key: Vec<u8>, log_level: LogLevel) -> Self {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
-        Self {
-            file_url_template,
+impl Settings {
+    /// HTTP 客户端仅在其配置变化时重建, 以保留连接池
+    fn new(config: &Config, previous: Option<&Settings>) -> Result<Self, reqwest::Error> {
+        let client = match previous {
+            Some(prev) if prev.http_client == config.http_client => prev.client.clone(),
+            _ => build_client(&config.http_client)?,
+        };
+        Ok(Self {
+            file_url_template: config.file_url_template.clone(),
+            http_client: config.http_client.clone(),
             client,
-            sign_key,
-            log_level,
-        }
+            sign_key: Secret::new(config.sign_key.expose().as_bytes().to_vec()),
+            admin_token: config.admin_token.clone(),
+            log_lang: config.log_lang,
+        })
     }
+}
 
-    fn get_level_str(log_level: LogLevel) -> &'static str {
-        match log_level {
-            LogLevel::DEBUG => "DEBUG",
-            LogLevel::INFO => "INFO",
-            LogLevel::WARN => "WARN",
-            LogLevel::ERROR => "ERROR",
-            LogLevel::UNKNOWN => "UNKNOWN",
-        }
+pub struct ServerUtils {
+    settings: ArcSwap<Settings>,
+    /// 与定期清理任务一致, 修改后需要重启
+    retention: RetentionPolicy,
+}
+
+impl ServerUtils {
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            settings: ArcSwap::from_pointee(Settings::new(config, None)?),
+            retention: config.retention.policy(),
+        })
+    }
+
+    /// 原子替换全部可热更新的设置, 进行中的请求继续使用旧值
+    pub fn reload(&self, config: &Config) -> Result<(), reqwest::Error> {
+        let next = Settings::new(config, Some(&self.settings.load()))?;
+        self.settings.store(Arc::new(next));
+        Ok(())
     }
 }
 
 #[async_trait]
 impl AppUtils for ServerUtils {
-    async fn get_file(&self, file_obj_id: &str) -> Vec<u8> {
-        let url = self.file_url_template.replace("{file_obj_id}", file_obj_id);
-        let resp = self
-            .client
-            .get(&url)
-            .send()
+    async fn get_file(&self, file_obj_id: &str) -> Result<Vec<u8>, Error> {
+        let settings = self.settings.load_full();
+        let url = settings
+            .file_url_template
+            .replace("{file_obj_id}", file_obj_id);
+        let start = Instant::now();
+        let result = async {
+            let resp = settings.client.get(&url).send().await?.error_for_status()?;
+            resp.bytes().await
+        }
+        .await;
+        METRICS.observe_fetch(start, result.is_ok());
+        result
+            .map(|b| b.to_vec())
+            .map_err(|e| Error::Fetch(e.to_string()))
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        let template = self.settings.load().file_url_template.clone();
+        let url = Url::parse(&template).map_err(|e| Error::Fetch(e.to_string()))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::Fetch(format!("no host in '{}'", template)))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+        tokio::net::lookup_host((host, port))
             .await
-            .expect("Failed to get file");
-        resp.bytes().await.expect("Failed to read bytes").to_vec()
+            .map_err(|e| Error::Fetch(format!("{}: {}", host, e)))?
+            .next()
+            .map(|_| ())
+            .ok_or_else(|| Error::Fetch(format!("{}: no address", host)))
     }
 
     fn sign(&self, data: &[u8]) -> String {
-        sign(&self.sign_key, data)
+        sign(self.settings.load().sign_key.expose(), data)
     }
 
-    fn logger(&self, level: LogLevel, msg: &str) {
-        if self.log_level as u8 <= level as u8 {
-            println!("[{}] {}", Self::get_level_str(level), msg);
-        }
+    fn request_id(&self) -> String {
+        Uuid::new_v4().simple().to_string()
     }
-}
 
-pub async fn handler_404() -> impl IntoResponse {
-    (StatusCode::NOT_FOUND, "Not Found :(")
+    fn log_lang(&self) -> Lang {
+        self.settings.load().log_lang
+    }
+
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+
+    fn record(&self, metric: Metric<'_>) {
+        METRICS.record(metric);
+    }
+
+    fn admin_token(&self) -> Option<Secret> {
+        self.settings.load().admin_token.clone()
+    }
+
+    fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
 }
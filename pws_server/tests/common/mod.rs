@@ -0,0 +1,63 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pws_server::kv::{Backend, FsBlobStore, RedbKVStorage, Storage};
+
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+/// 测试结束时删除的临时目录
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let n = NEXT.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("pws-{}-{}-{}", name, process::id(), n));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+pub fn blobs(dir: &TempDir) -> FsBlobStore {
+    FsBlobStore::new(dir.join("blobs")).unwrap()
+}
+
+pub fn redb(dir: &TempDir) -> Storage {
+    let path = dir.join("pws.redb").to_str().unwrap().to_owned();
+    Storage::new(Backend::Redb(RedbKVStorage::new(path).unwrap()), blobs(dir))
+}
+
+#[cfg(feature = "sqlite")]
+pub fn sqlite(dir: &TempDir) -> Storage {
+    use pws_server::kv::SqliteKVStorage;
+
+    let path = dir.join("pws.sqlite");
+    let kv = SqliteKVStorage::new(path.to_str().unwrap()).unwrap();
+    Storage::new(Backend::Sqlite(kv), blobs(dir))
+}
@@ -0,0 +1,350 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Mutex;
+
+use pws_core::i18n::Lang;
+use pws_core::secret::REDACTED;
+use pws_core::sign::{MAX_KEY_LEN, sign};
+use pws_core::types::LogLevel;
+use pws_server::config::{Config, ConfigError, StorageConfig};
+use pws_server::types::{KvBackend, LogFormat};
+
+/// 环境变量在进程内共享, 读写它们的测试需要串行执行
+static ENV: Mutex<()> = Mutex::new(());
+
+const TOML: &str = r#"
+log_level = "INFO"
+log_lang = "en"
+kv_storage_path = "./pws.redb"
+sign_key = "secret"
+file_url_template = "https://example.com/{file_obj_id}"
+admin_token = "admin"
+
+[server]
+port = 8080
+
+[retention]
+keep_history = 3
+"#;
+
+const JSON: &str = r#"{
+    "log_level": "INFO",
+    "log_lang": "en",
+    "kv_storage_path": "./pws.redb",
+    "sign_key": "secret",
+    "file_url_template": "https://example.com/{file_obj_id}",
+    "admin_token": "admin",
+    "server": { "port": 8080 },
+    "retention": { "keep_history": 3 }
+}"#;
+
+fn write(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pws-config-{}-{}", process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(name: &str, contents: &str) -> Result<Config, ConfigError> {
+    let path = write(name, contents);
+    let result = Config::load(Some(path.to_str().unwrap()));
+    fs::remove_file(path).unwrap();
+    result
+}
+
+fn load_storage(name: &str, contents: &str) -> Result<StorageConfig, ConfigError> {
+    let path = write(name, contents);
+    let result = StorageConfig::load(Some(path.to_str().unwrap()));
+    fs::remove_file(path).unwrap();
+    result
+}
+
+fn json_with(field: &str, value: serde_json::Value) -> String {
+    let mut config: serde_json::Value = serde_json::from_str(JSON).unwrap();
+    config[field] = value;
+    config.to_string()
+}
+
+#[test]
+fn toml_and_json_files_load_the_same_config() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let toml = load("a.toml", TOML).unwrap();
+    let json = load("a.json", JSON).unwrap();
+    // 非 `.toml` 扩展名一律按 JSON 解析
+    let other = load("a.conf", JSON).unwrap();
+
+    for config in [&toml, &json, &other] {
+        assert_eq!(config.log_level, LogLevel::INFO);
+        assert_eq!(config.log_lang, Lang::En);
+        assert_eq!(config.log_format, LogFormat::Human);
+        assert_eq!(config.sign_key.expose(), "secret");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.retention.keep_history, 3);
+        assert_eq!(config.blobs.path, "./blobs");
+    }
+    assert_eq!(toml.diff(&json), (vec![], vec![]));
+    assert_eq!(json.diff(&other), (vec![], vec![]));
+
+    assert!(matches!(
+        load("bad.toml", JSON),
+        Err(ConfigError::Parse { .. })
+    ));
+    assert!(matches!(
+        load("bad.json", TOML),
+        Err(ConfigError::Parse { .. })
+    ));
+    assert!(matches!(
+        load("list.json", "[]"),
+        Err(ConfigError::Parse { .. })
+    ));
+    assert!(matches!(
+        Config::load(Some("/nonexistent/pws.toml")),
+        Err(ConfigError::Read { .. })
+    ));
+}
+
+#[test]
+fn env_overrides_file_values() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let path = write("env.toml", TOML);
+    let path = path.to_str().unwrap();
+    let vars = [
+        ("PWS_SIGN_KEY", "from-env"),
+        ("PWS_SERVER_PORT", "9090"),
+        ("PWS_LOG_FORMAT", "json"),
+        ("PWS_RETENTION_DRY_RUN", "1"),
+        ("PWS_BLOBS_PATH", "/var/lib/pws/blobs"),
+        ("PWS_SNAPSHOTS_PATH", "/var/lib/pws/snapshots"),
+    ];
+    // SAFETY: 测试通过 ENV 串行访问环境变量
+    unsafe {
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+    }
+    let config = Config::load(Some(path));
+    unsafe {
+        env::set_var("PWS_SERVER_PORT", "not-a-port");
+    }
+    let invalid = Config::load(Some(path));
+    unsafe {
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+    }
+    fs::remove_file(path).unwrap();
+
+    let config = config.unwrap();
+    assert_eq!(config.sign_key.expose(), "from-env");
+    assert_eq!(config.server.port, 9090);
+    assert_eq!(config.log_format, LogFormat::Json);
+    assert!(config.retention.dry_run);
+    assert_eq!(config.retention.keep_history, 3);
+    assert_eq!(config.blobs.path, "/var/lib/pws/blobs");
+    // 只设置了嵌套表的部分字段时, 其余字段取默认值
+    let snapshots = config.snapshots.unwrap();
+    assert_eq!(
+        (snapshots.path.as_str(), snapshots.keep),
+        ("/var/lib/pws/snapshots", 7)
+    );
+
+    match invalid {
+        Err(ConfigError::Env { name, value }) => {
+            assert_eq!(
+                (name.as_str(), value.as_str()),
+                ("PWS_SERVER_PORT", "not-a-port")
+            )
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let field =
+        |name: &str, value: serde_json::Value| load("invalid.json", &json_with(name, value));
+
+    assert!(matches!(
+        field("file_url_template", "https://example.com/file".into()),
+        Err(ConfigError::MissingPlaceholder(_))
+    ));
+    assert!(matches!(
+        field("file_url_template", "not a url {file_obj_id}".into()),
+        Err(ConfigError::Invalid {
+            field: "file_url_template",
+            ..
+        })
+    ));
+    // 未知的日志等级会被解析为 `UNKNOWN`, 不能静默关闭日志
+    assert!(matches!(
+        field("log_level", "info".into()),
+        Err(ConfigError::Invalid {
+            field: "log_level",
+            ..
+        })
+    ));
+    assert!(matches!(
+        field("sign_key", "".into()),
+        Err(ConfigError::Empty("sign_key"))
+    ));
+    assert!(matches!(
+        field("admin_token", "".into()),
+        Err(ConfigError::Empty("admin_token"))
+    ));
+    assert!(matches!(
+        field("server", serde_json::json!({ "host": "localhost" })),
+        Err(ConfigError::Invalid {
+            field: "server.host",
+            ..
+        })
+    ));
+    assert!(matches!(
+        field("server", serde_json::json!({ "request_timeout_secs": 0 })),
+        Err(ConfigError::NotPositive("server.request_timeout_secs"))
+    ));
+    assert!(matches!(
+        field("server", serde_json::json!({ "port": 70000 })),
+        Err(ConfigError::Parse { .. })
+    ));
+    assert!(matches!(
+        field(
+            "snapshots",
+            serde_json::json!({ "path": "./snapshots", "keep": 0 })
+        ),
+        Err(ConfigError::NotPositive("snapshots.keep"))
+    ));
+
+    let tls = |extra: serde_json::Value| {
+        let mut value = serde_json::json!({ "cert_path": "cert.pem", "key_path": "key.pem" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        field("tls", value)
+    };
+    assert!(matches!(
+        tls(serde_json::json!({ "redirect_port": 80 })),
+        Err(ConfigError::Empty("tls.public_host"))
+    ));
+    for host in [
+        "pws.example.com:8443",
+        "https://pws.example.com",
+        "pws.example.com/x",
+        "a@b",
+    ] {
+        assert!(
+            matches!(
+                tls(serde_json::json!({ "redirect_port": 80, "public_host": host })),
+                Err(ConfigError::Invalid {
+                    field: "tls.public_host",
+                    ..
+                })
+            ),
+            "{host}"
+        );
+    }
+    assert!(
+        tls(serde_json::json!({ "redirect_port": 80, "public_host": "pws.example.com" })).is_ok()
+    );
+}
+
+#[test]
+fn sign_key_longer_than_blake2s_limit_is_rejected() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let longest = "k".repeat(MAX_KEY_LEN);
+    let config = load("key.json", &json_with("sign_key", longest.clone().into())).unwrap();
+    // 上限以内的密钥可以正常签名
+    assert!(!sign(config.sign_key.expose().as_bytes(), b"body").is_empty());
+
+    let too_long = format!("{}k", longest);
+    assert!(matches!(
+        load("key.json", &json_with("sign_key", too_long.into())),
+        Err(ConfigError::Invalid {
+            field: "sign_key",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn diff_compares_values_and_flags_restart_fields() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let old = load("old.json", JSON).unwrap();
+    let mut new = old.clone();
+    new.log_level = LogLevel::DEBUG;
+    new.http_client.timeout_secs = 5;
+    new.log_format = LogFormat::Json;
+    new.server.port = 8081;
+    new.blobs.gc_grace_secs = 60;
+
+    let (changes, restart) = old.diff(&new);
+    assert_eq!(
+        changes,
+        [
+            "log_level: INFO -> DEBUG".to_owned(),
+            format!(
+                "http_client: {:?} -> {:?}",
+                old.http_client, new.http_client
+            ),
+        ]
+    );
+    assert_eq!(restart, ["log_format", "blobs", "server"]);
+}
+
+#[test]
+fn storage_config_needs_only_storage_fields() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let target = "kv_backend = \"redb\"\nkv_storage_path = \"./new.redb\"\n\n[blobs]\npath = \"./new-blobs\"\n";
+    let storage = load_storage("target.toml", target).unwrap();
+    assert_eq!(storage.kv_backend, KvBackend::Redb);
+    assert_eq!(storage.kv_storage_path, "./new.redb");
+    assert_eq!(storage.blobs.path, "./new-blobs");
+    // 完整配置还要求 sign_key 等字段
+    assert!(matches!(
+        load("target.toml", target),
+        Err(ConfigError::Parse { .. })
+    ));
+
+    let config = load("full.json", JSON).unwrap();
+    assert_eq!(load_storage("full.json", JSON).unwrap(), config.storage());
+
+    assert!(matches!(
+        load_storage("empty.toml", "kv_storage_path = \"\"\n"),
+        Err(ConfigError::Empty("kv_storage_path"))
+    ));
+}
+
+#[test]
+fn secrets_are_redacted_in_debug_and_diff() {
+    let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let with_secrets = |sign_key: &str, admin_token: &str| {
+        let mut config: serde_json::Value = serde_json::from_str(JSON).unwrap();
+        config["sign_key"] = sign_key.into();
+        config["admin_token"] = admin_token.into();
+        load("secrets.json", &config.to_string()).unwrap()
+    };
+    let old = with_secrets("sign-key-5c1e", "admin-token-9d2b");
+    let new = with_secrets("sign-key-77a0", "admin-token-e41f");
+    let secrets = [
+        "sign-key-5c1e",
+        "admin-token-9d2b",
+        "sign-key-77a0",
+        "admin-token-e41f",
+    ];
+
+    let debug = format!("{:?}", old);
+    assert!(debug.contains(REDACTED));
+    let (changes, restart) = old.diff(&new);
+    assert!(restart.is_empty());
+    assert_eq!(changes.len(), 2);
+    assert!(changes[0].starts_with("sign_key: ") && changes[0].contains(REDACTED));
+    assert!(changes[1].starts_with("admin_token: ") && changes[1].contains(REDACTED));
+    for output in changes.iter().chain([&debug]) {
+        for secret in secrets {
+            assert!(!output.contains(secret), "{} leaked in {}", secret, output);
+        }
+    }
+}
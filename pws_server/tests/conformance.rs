@@ -0,0 +1,125 @@
+//! 所有 KV 后端都应通过的同一组行为测试
+
+mod common;
+
+use pws_core::store::blob_hash;
+use pws_core::testing::MemoryKVStorage;
+use pws_core::types::{BlobStore, KVStorage, KVTable, PutOptions};
+use serde_json::json;
+
+use common::TempDir;
+
+/// 用 [`KVTable::list_page`] 逐页列出, 返回每一页的键
+async fn pages<T: KVTable>(table: &T, prefix: &str, limit: usize) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = table
+            .list_page(prefix, cursor.as_deref(), limit)
+            .await
+            .unwrap();
+        pages.push(page.keys);
+        cursor = page.cursor;
+        if cursor.is_none() {
+            return pages;
+        }
+    }
+}
+
+async fn conformance<KV: KVStorage>(kv: &KV, now: u64) {
+    let user = kv.open_table("user").await.unwrap();
+    let save = kv.open_table("save").await.unwrap();
+
+    assert_eq!(user.get("a").await.unwrap(), None);
+    user.put("a", b"1").await.unwrap();
+    user.put("a", b"2").await.unwrap();
+    assert_eq!(user.get("a").await.unwrap().as_deref(), Some(&b"2"[..]));
+    // 表之间互相隔离, 重复打开同一张表看到相同内容
+    assert_eq!(save.get("a").await.unwrap(), None);
+    let again = kv.open_table("user").await.unwrap();
+    assert_eq!(again.get("a").await.unwrap().as_deref(), Some(&b"2"[..]));
+
+    for key in ["b/2", "b/1", "c", "b%", "b/10"] {
+        user.put(key, key.as_bytes()).await.unwrap();
+    }
+    assert_eq!(user.list("b/").await.unwrap(), ["b/1", "b/10", "b/2"]);
+    assert_eq!(user.list("b%").await.unwrap(), ["b%"]);
+    assert_eq!(
+        user.list("").await.unwrap(),
+        ["a", "b%", "b/1", "b/10", "b/2", "c"]
+    );
+    assert!(save.list("").await.unwrap().is_empty());
+
+    user.delete("b/1").await.unwrap();
+    user.delete("missing").await.unwrap();
+    assert_eq!(user.get("b/1").await.unwrap(), None);
+    assert_eq!(user.list("b/").await.unwrap(), ["b/10", "b/2"]);
+    assert_eq!(
+        pages(&user, "", 2).await,
+        [vec!["a", "b%"], vec!["b/10", "b/2"], vec!["c"]]
+    );
+    assert_eq!(pages(&user, "b/", 1).await, [["b/10"], ["b/2"]]);
+    assert_eq!(pages(&user, "d", 10).await, [Vec::<String>::new()]);
+
+    let metadata = json!({ "source": "test" }).as_object().cloned().unwrap();
+    let options = PutOptions::default()
+        .with_metadata(metadata.clone())
+        .with_ttl(now, 3600);
+    save.put_with_options("m", b"v", &options).await.unwrap();
+    let entry = save.get_with_metadata("m").await.unwrap().unwrap();
+    assert_eq!(entry.value, b"v");
+    assert_eq!(entry.metadata, Some(metadata));
+    assert_eq!(entry.expires_at, Some(now + 3_600_000));
+    save.put("m", b"w").await.unwrap();
+    let entry = save.get_with_metadata("m").await.unwrap().unwrap();
+    assert_eq!((entry.metadata, entry.expires_at), (None, None));
+
+    let expired = PutOptions {
+        expires_at: Some(now - 1),
+        ..PutOptions::default()
+    };
+    save.put_with_options("old", b"v", &expired).await.unwrap();
+    assert_eq!(save.get("old").await.unwrap(), None);
+    assert!(save.get_with_metadata("old").await.unwrap().is_none());
+    assert_eq!(save.list("").await.unwrap(), ["m"]);
+    assert_eq!(pages(&save, "", 10).await, [["m"]]);
+
+    let blobs = kv.blobs();
+    let hash = blob_hash(b"zip");
+    blobs.put(&hash, b"zip").await.unwrap();
+    blobs.put(&hash, b"zip").await.unwrap();
+    assert_eq!(
+        blobs.get(&hash).await.unwrap().as_deref(),
+        Some(&b"zip"[..])
+    );
+    let listed = blobs.list().await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].hash, hash);
+    blobs.delete(&hash).await.unwrap();
+    blobs.delete(&hash).await.unwrap();
+    assert_eq!(blobs.get(&hash).await.unwrap(), None);
+    assert!(blobs.list().await.unwrap().is_empty());
+
+    kv.ready().await.unwrap();
+}
+
+#[tokio::test]
+async fn memory_backend_conforms() {
+    let kv = MemoryKVStorage::new();
+    let now = common::now();
+    kv.advance(now);
+    conformance(&kv, now).await;
+}
+
+#[tokio::test]
+async fn redb_backend_conforms() {
+    let dir = TempDir::new("conformance-redb");
+    conformance(&common::redb(&dir), common::now()).await;
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_backend_conforms() {
+    let dir = TempDir::new("conformance-sqlite");
+    conformance(&common::sqlite(&dir), common::now()).await;
+}
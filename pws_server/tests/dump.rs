@@ -0,0 +1,131 @@
+mod common;
+
+use common::{TempDir, now};
+use pws_core::audit::AUDIT_TABLE;
+use pws_core::dump;
+use pws_core::store;
+use pws_core::types::{BlobStore, Entry, KVStorage, KVTable, PutOptions, TABLES};
+use pws_server::kv::Storage;
+use serde_json::json;
+
+async fn populate(kv: &Storage, now: u64) {
+    store::put_user(kv, "open-1", "Alice", now).await.unwrap();
+    store::put_save(kv, "open-1", "file-1", b"zip-1", None, now, 1)
+        .await
+        .unwrap();
+    store::put_save(kv, "open-1", "file-2", b"zip-2", None, now + 1, 1)
+        .await
+        .unwrap();
+    store::put_user(kv, "open-2", "Bob", now).await.unwrap();
+    let metadata = json!({ "source": "test" }).as_object().unwrap().clone();
+    let options = PutOptions::default()
+        .with_metadata(metadata)
+        .with_ttl(now, 3600);
+    kv.open_table(AUDIT_TABLE)
+        .await
+        .unwrap()
+        .put_with_options("open-1", b"audit", &options)
+        .await
+        .unwrap();
+}
+
+/// 每张表的全部记录, 包括元数据与过期时间
+async fn contents(kv: &Storage) -> Vec<(&'static str, String, Entry)> {
+    let mut records = Vec::new();
+    for &name in TABLES {
+        let table = kv.open_table(name).await.unwrap();
+        for key in table.list("").await.unwrap() {
+            let entry = table.get_with_metadata(&key).await.unwrap().unwrap();
+            records.push((name, key, entry));
+        }
+    }
+    records
+}
+
+async fn blobs(kv: &Storage) -> Vec<(String, Vec<u8>)> {
+    let mut hashes: Vec<String> = kv
+        .blobs()
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|b| b.hash)
+        .collect();
+    hashes.sort();
+    let mut blobs = Vec::new();
+    for hash in hashes {
+        let data = kv.blobs().get(&hash).await.unwrap().unwrap();
+        blobs.push((hash, data));
+    }
+    blobs
+}
+
+#[tokio::test]
+async fn redb_dump_and_load_round_trip() {
+    let now = now();
+    let source_dir = TempDir::new("dump-source");
+    let source = common::redb(&source_dir);
+    populate(&source, now).await;
+
+    let dumped = source_dir.join("pws.ndjson");
+    let exported = dump::export(&source, std::fs::File::create(&dumped).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        exported,
+        dump::Stats {
+            records: contents(&source).await.len() as u64,
+            blobs: 2
+        }
+    );
+
+    let target_dir = TempDir::new("dump-target");
+    {
+        let target = common::redb(&target_dir);
+        let input = std::io::BufReader::new(std::fs::File::open(&dumped).unwrap());
+        assert_eq!(dump::import(&target, input).await.unwrap(), exported);
+        target.flush().unwrap();
+    }
+
+    // 重新打开数据库, 确认导入的内容已经落盘
+    let target = common::redb(&target_dir);
+    assert_eq!(contents(&target).await, contents(&source).await);
+    assert_eq!(blobs(&target).await, blobs(&source).await);
+    assert_eq!(
+        store::get_save(&target, "open-1").await.unwrap().as_deref(),
+        Some(&b"zip-2"[..])
+    );
+    let audit = target
+        .open_table(AUDIT_TABLE)
+        .await
+        .unwrap()
+        .get_with_metadata("open-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(audit.expires_at, Some(now + 3600 * 1000));
+    assert_eq!(audit.metadata.unwrap()["source"], "test");
+}
+
+async fn check_copy(source: &Storage, target: &Storage) {
+    let copied = dump::copy(source, target).await.unwrap();
+    assert_eq!(copied.blobs, 2);
+    assert_eq!(contents(target).await, contents(source).await);
+    assert_eq!(blobs(target).await, blobs(source).await);
+}
+
+#[tokio::test]
+async fn migrate_copies_between_backends() {
+    let now = now();
+    let source_dir = TempDir::new("migrate-source");
+    let source = common::redb(&source_dir);
+    populate(&source, now).await;
+
+    let redb_dir = TempDir::new("migrate-redb");
+    check_copy(&source, &common::redb(&redb_dir)).await;
+    #[cfg(feature = "sqlite")]
+    {
+        let sqlite_dir = TempDir::new("migrate-sqlite");
+        check_copy(&source, &common::sqlite(&sqlite_dir)).await;
+    }
+}
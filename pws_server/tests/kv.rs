@@ -0,0 +1,150 @@
+//! redb 与 sqlite 的过期时间与 `#meta` 附表
+
+mod common;
+
+use std::path::Path;
+
+use pws_core::types::{KVStorage, KVTable, PutOptions};
+use pws_server::kv::Storage;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+use serde_json::json;
+
+use common::{TempDir, now};
+
+const TABLE: &str = "user";
+const META: &str = "user#meta";
+
+fn expiring(expires_at: u64) -> PutOptions {
+    PutOptions {
+        metadata: None,
+        expires_at: Some(expires_at),
+    }
+}
+
+async fn expiry_is_applied_on_read(kv: &Storage, now: u64) {
+    let table = kv.open_table(TABLE).await.unwrap();
+    table
+        .put_with_options("expired", b"1", &expiring(now - 1000))
+        .await
+        .unwrap();
+    let future = PutOptions::default()
+        .with_metadata(json!({ "source": "test" }).as_object().unwrap().clone())
+        .with_ttl(now, 3600);
+    table.put_with_options("live", b"2", &future).await.unwrap();
+
+    assert_eq!(table.get("expired").await.unwrap(), None);
+    assert_eq!(table.get_with_metadata("expired").await.unwrap(), None);
+    assert_eq!(table.list("").await.unwrap(), ["live"]);
+    let live = table.get_with_metadata("live").await.unwrap().unwrap();
+    assert_eq!(live.value, b"2");
+    assert_eq!(live.expires_at, future.expires_at);
+    assert_eq!(live.metadata, future.metadata);
+
+    // 过期的键被重新写入后恢复可见
+    table.put("expired", b"3").await.unwrap();
+    assert_eq!(
+        table.get("expired").await.unwrap().as_deref(),
+        Some(&b"3"[..])
+    );
+}
+
+/// 写入后清理过期记录, 返回后由调用方检查底层的表
+async fn sweep_and_delete(kv: Storage, now: u64) {
+    let table = kv.open_table(TABLE).await.unwrap();
+    for key in ["a", "b"] {
+        table
+            .put_with_options(key, b"x", &expiring(now - 1))
+            .await
+            .unwrap();
+    }
+    table
+        .put_with_options("c", b"x", &expiring(now + 3_600_000))
+        .await
+        .unwrap();
+    table.put("d", b"x").await.unwrap();
+    // 带元数据写入后再普通写入, 元数据被清除
+    table
+        .put_with_options("e", b"x", &expiring(now + 3_600_000))
+        .await
+        .unwrap();
+    table.put("e", b"y").await.unwrap();
+    assert_eq!(
+        table
+            .get_with_metadata("e")
+            .await
+            .unwrap()
+            .unwrap()
+            .expires_at,
+        None
+    );
+    // 删除时一并删除元数据
+    table
+        .put_with_options("f", b"x", &expiring(now + 3_600_000))
+        .await
+        .unwrap();
+    table.delete("f").await.unwrap();
+
+    assert_eq!(kv.sweep_expired(now).unwrap(), 2);
+    assert_eq!(kv.sweep_expired(now).unwrap(), 0);
+    assert_eq!(table.list("").await.unwrap(), ["c", "d", "e"]);
+    kv.flush().unwrap();
+}
+
+fn redb_keys(path: &Path, name: &str) -> Vec<String> {
+    let db = Database::open(path).unwrap();
+    let txn = db.begin_read().unwrap();
+    let table = txn
+        .open_table(TableDefinition::<&str, Vec<u8>>::new(name))
+        .unwrap();
+    table
+        .iter()
+        .unwrap()
+        .map(|entry| entry.unwrap().0.value().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn redb_expires_entries_on_read() {
+    let dir = TempDir::new("kv-redb-expiry");
+    expiry_is_applied_on_read(&common::redb(&dir), now()).await;
+}
+
+#[tokio::test]
+async fn redb_sweep_keeps_meta_consistent() {
+    let dir = TempDir::new("kv-redb-sweep");
+    sweep_and_delete(common::redb(&dir), now()).await;
+
+    let path = dir.join("pws.redb");
+    assert_eq!(redb_keys(&path, TABLE), ["c", "d", "e"]);
+    assert_eq!(redb_keys(&path, META), ["c"]);
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_keys(path: &Path, name: &str) -> Vec<String> {
+    let conn = rusqlite::Connection::open(path).unwrap();
+    let mut stmt = conn
+        .prepare(&format!("SELECT key FROM \"{}\" ORDER BY key", name))
+        .unwrap();
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_expires_entries_on_read() {
+    let dir = TempDir::new("kv-sqlite-expiry");
+    expiry_is_applied_on_read(&common::sqlite(&dir), now()).await;
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_sweep_keeps_meta_consistent() {
+    let dir = TempDir::new("kv-sqlite-sweep");
+    sweep_and_delete(common::sqlite(&dir), now()).await;
+
+    let path = dir.join("pws.sqlite");
+    assert_eq!(sqlite_keys(&path, TABLE), ["c", "d", "e"]);
+    assert_eq!(sqlite_keys(&path, META), ["c"]);
+}
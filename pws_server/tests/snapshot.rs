@@ -0,0 +1,178 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{TempDir, now};
+use pws_core::store::{self, blob_hash};
+use pws_core::types::{BlobStore, KVStorage};
+use pws_server::config::{BlobConfig, SnapshotConfig, StorageConfig};
+use pws_server::kv::Storage;
+use pws_server::snapshot::{self, Restore, RestoreError};
+use pws_server::types::KvBackend;
+
+fn storage_config(dir: &TempDir) -> StorageConfig {
+    StorageConfig {
+        kv_backend: KvBackend::Redb,
+        kv_storage_path: dir.join("pws.redb").to_str().unwrap().to_owned(),
+        blobs: BlobConfig {
+            path: dir.join("blobs").to_str().unwrap().to_owned(),
+            ..BlobConfig::default()
+        },
+    }
+}
+
+fn snapshot_config(dir: &TempDir, keep: usize) -> SnapshotConfig {
+    SnapshotConfig {
+        path: dir.join("snapshots").to_str().unwrap().to_owned(),
+        interval_secs: 3600,
+        keep,
+    }
+}
+
+/// 写入存档后快照, 再用新存档覆盖并回收旧存档文件. 返回快照路径.
+async fn snapshot_then_collect(dir: &TempDir, now: u64) -> std::path::PathBuf {
+    let kv = common::redb(dir);
+    store::put_user(&kv, "open-1", "Alice", now).await.unwrap();
+    store::put_save(&kv, "open-1", "file-1", b"zip-1", None, now, 0)
+        .await
+        .unwrap();
+    let taken = snapshot::take(
+        kv.redb().unwrap(),
+        kv.blobs(),
+        &snapshot_config(dir, 2),
+        now,
+    )
+    .await
+    .unwrap();
+    assert_eq!(taken.blobs, 1);
+
+    store::put_save(&kv, "open-1", "file-2", b"zip-2", None, now + 1, 0)
+        .await
+        .unwrap();
+    let collected = store::collect_blobs(&kv, u64::MAX).await.unwrap();
+    assert_eq!(collected, [blob_hash(b"zip-1")]);
+    kv.flush().unwrap();
+    taken.path
+}
+
+fn staged(dir: &TempDir) -> bool {
+    Path::new(&format!("{}.restore", dir.join("pws.redb").display())).exists()
+}
+
+#[tokio::test]
+async fn restore_recovers_blobs_collected_after_the_snapshot() {
+    let dir = TempDir::new("snapshot-restore");
+    let now = now();
+    let path = snapshot_then_collect(&dir, now).await;
+    assert!(
+        snapshot::blobs_dir(&path)
+            .join(&blob_hash(b"zip-1")[..2])
+            .join(blob_hash(b"zip-1"))
+            .exists()
+    );
+
+    // 只校验时不做任何改动
+    let check = Restore::prepare(&path, &storage_config(&dir))
+        .await
+        .unwrap();
+    assert!(check.records > 0);
+    assert_eq!(check.missing, 0);
+    drop(check);
+    assert!(!staged(&dir));
+    assert_eq!(
+        store::get_save(&common::redb(&dir), "open-1")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(&b"zip-2"[..])
+    );
+
+    let mut restore = Restore::prepare(&path, &storage_config(&dir))
+        .await
+        .unwrap();
+    let (blobs, backup) = restore.apply(now + 2).unwrap();
+    drop(restore);
+    assert_eq!(blobs, 1);
+    assert!(backup.unwrap().exists());
+    assert!(!staged(&dir));
+
+    let kv = common::redb(&dir);
+    assert_eq!(
+        store::get_save(&kv, "open-1").await.unwrap().as_deref(),
+        Some(&b"zip-1"[..])
+    );
+    drop(kv);
+    // 恢复后的数据库引用的存档文件全部存在
+    let verified = Restore::prepare(&path, &storage_config(&dir))
+        .await
+        .unwrap();
+    assert_eq!(verified.missing, 0);
+}
+
+#[tokio::test]
+async fn restore_reports_blobs_missing_from_both_places() {
+    let dir = TempDir::new("snapshot-missing");
+    let path = snapshot_then_collect(&dir, now()).await;
+    fs::remove_dir_all(snapshot::blobs_dir(&path)).unwrap();
+
+    let restore = Restore::prepare(&path, &storage_config(&dir))
+        .await
+        .unwrap();
+    assert_eq!(restore.missing, 1);
+    drop(restore);
+    assert!(!staged(&dir));
+}
+
+#[tokio::test]
+async fn restore_refuses_while_the_database_is_open() {
+    let dir = TempDir::new("snapshot-locked");
+    let path = snapshot_then_collect(&dir, now()).await;
+
+    let kv: Storage = common::redb(&dir);
+    let result = Restore::prepare(&path, &storage_config(&dir)).await;
+    assert!(matches!(result, Err(RestoreError::InUse(_))));
+    assert!(!staged(&dir));
+    drop(kv);
+
+    assert!(Restore::prepare(&path, &storage_config(&dir)).await.is_ok());
+}
+
+#[tokio::test]
+async fn damaged_snapshot_is_rejected() {
+    let dir = TempDir::new("snapshot-damaged");
+    let path = snapshot_then_collect(&dir, now()).await;
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+    let result = Restore::prepare(&path, &storage_config(&dir)).await;
+    assert!(matches!(result, Err(RestoreError::Storage(_))));
+    assert!(!staged(&dir));
+}
+
+#[tokio::test]
+async fn pruning_removes_old_snapshots_with_their_blobs() {
+    let dir = TempDir::new("snapshot-prune");
+    let kv = common::redb(&dir);
+    let now = now();
+    store::put_save(&kv, "open-1", "file-1", b"zip-1", None, now, 0)
+        .await
+        .unwrap();
+    let config = snapshot_config(&dir, 1);
+    let first = snapshot::take(kv.redb().unwrap(), kv.blobs(), &config, now)
+        .await
+        .unwrap();
+    let second = snapshot::take(kv.redb().unwrap(), kv.blobs(), &config, now + 1)
+        .await
+        .unwrap();
+
+    assert_eq!((first.pruned, second.pruned), (0, 1));
+    assert!(!first.path.exists());
+    assert!(!snapshot::blobs_dir(&first.path).exists());
+    assert!(second.path.exists());
+    assert_eq!(
+        kv.blobs().list().await.unwrap().len(),
+        1,
+        "pruning must not touch the live blobs"
+    );
+}
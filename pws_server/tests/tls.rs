@@ -0,0 +1,34 @@
+use axum::body::Body;
+use axum::http::header::{HOST, LOCATION};
+use axum::http::{Request, StatusCode};
+use pws_server::tls::redirect_router;
+use tower::ServiceExt;
+
+async fn location(https_port: u16, host: Option<&str>, uri: &str) -> String {
+    let mut req = Request::get(uri);
+    if let Some(host) = host {
+        req = req.header(HOST, host);
+    }
+    let resp = redirect_router("pws.example.com", https_port)
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+    resp.headers()[LOCATION].to_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn redirect_ignores_the_request_host() {
+    assert_eq!(
+        location(443, Some("evil.example.net"), "/info/open-1?x=1").await,
+        "https://pws.example.com/info/open-1?x=1"
+    );
+    assert_eq!(
+        location(8443, Some("evil.example.net:80"), "/").await,
+        "https://pws.example.com:8443/"
+    );
+    assert_eq!(
+        location(443, None, "/health").await,
+        "https://pws.example.com/health"
+    );
+}
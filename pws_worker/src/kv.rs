@@ -1,51 +1,168 @@
 use async_trait::async_trait;
-use pws_core::types::{KVStorage, KVTable};
+use pws_core::error::Error;
+use pws_core::store::{BLOB_TABLE, TableBlobStore};
+use pws_core::types::{Entry, KVStorage, KVTable, Page, PutOptions, TABLES};
 use worker::*;
 
 use crate::utils::UnsafeSend;
 
+fn storage_err(e: impl std::fmt::Display) -> Error {
+    Error::Storage(e.to_string())
+}
+
+/// Cloudflare KV 要求过期时间至少在 60 秒之后
+const MIN_EXPIRATION_SECS: u64 = 60;
+
+/// 就绪检查读取的键, 不会被写入
+const READY_PROBE_KEY: &str = "__ready__";
+
 #[derive(Clone)]
 pub struct WorkerKVTable {
     pub table: KvStore,
 }
 
-#[derive(Clone)]
 pub struct WorkerKVStorage {
     pub env: Env,
+    blobs: TableBlobStore<WorkerKVTable>,
+}
+
+impl WorkerKVStorage {
+    /// 存档文件保存在 `blob` 命名空间中, 键为内容哈希
+    pub fn new(env: Env) -> Result<Self> {
+        let blobs = TableBlobStore::new(
+            WorkerKVTable {
+                table: env.kv(BLOB_TABLE)?,
+            },
+            || Date::now().as_millis(),
+        );
+        Ok(Self { env, blobs })
+    }
 }
 
 #[async_trait]
 impl KVStorage for WorkerKVStorage {
     type Table = WorkerKVTable;
+    type Blobs = TableBlobStore<WorkerKVTable>;
+
+    async fn open_table(&self, table: &str) -> std::result::Result<Self::Table, Error> {
+        Ok(WorkerKVTable {
+            table: self.env.kv(table).map_err(storage_err)?,
+        })
+    }
 
-    async fn open_table(&self, table: &str) -> Self::Table {
-        WorkerKVTable {
-            table: self.env.kv(table).expect("无效表"),
+    fn blobs(&self) -> &Self::Blobs {
+        &self.blobs
+    }
+
+    /// 对每个命名空间实际读取一个不存在的键, 绑定存在但不可访问时同样报告未就绪
+    async fn ready(&self) -> std::result::Result<(), Error> {
+        for table in TABLES.iter().chain([&BLOB_TABLE]) {
+            self.open_table(table)
+                .await?
+                .get(READY_PROBE_KEY)
+                .await
+                .map_err(|e| storage_err(format!("{}: {}", table, e)))?;
         }
+        Ok(())
     }
 }
 
 #[async_trait]
 impl KVTable for WorkerKVTable {
-    async fn get(&self, key: &str) -> Option<Vec<u8>> {
-        UnsafeSend(async move { self.table.get(key).bytes().await.unwrap() }).await
+    async fn get(&self, key: &str) -> std::result::Result<Option<Vec<u8>>, Error> {
+        Ok(self.get_with_metadata(key).await?.map(|entry| entry.value))
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> std::result::Result<(), Error> {
+        self.put_with_options(key, value, &PutOptions::default())
+            .await
+    }
+
+    async fn delete(&self, key: &str) -> std::result::Result<(), Error> {
+        UnsafeSend(async move { self.table.delete(key).await.map_err(storage_err) }).await
+    }
+
+    async fn list(&self, prefix: &str) -> std::result::Result<Vec<String>, Error> {
+        UnsafeSend(async move {
+            let mut keys = Vec::new();
+            let mut cursor = None;
+            loop {
+                let mut req = self.table.list().prefix(prefix.to_owned());
+                if let Some(c) = cursor {
+                    req = req.cursor(c);
+                }
+                let resp = req.execute().await.map_err(storage_err)?;
+                keys.extend(resp.keys.into_iter().map(|k| k.name));
+                if resp.list_complete {
+                    break;
+                }
+                cursor = resp.cursor;
+            }
+            Ok(keys)
+        })
+        .await
+    }
+
+    /// 直接使用 KV 原生的游标, 每页最多 1000 个
+    async fn list_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> std::result::Result<Page, Error> {
+        UnsafeSend(async move {
+            let mut req = self
+                .table
+                .list()
+                .prefix(prefix.to_owned())
+                .limit(limit.clamp(1, 1000) as u64);
+            if let Some(c) = cursor {
+                req = req.cursor(c.to_owned());
+            }
+            let resp = req.execute().await.map_err(storage_err)?;
+            Ok(Page {
+                keys: resp.keys.into_iter().map(|k| k.name).collect(),
+                cursor: resp.cursor.filter(|_| !resp.list_complete),
+            })
+        })
+        .await
     }
 
-    async fn put(&self, key: &str, value: &[u8]) {
+    /// 元数据以 [`PutOptions`] 的形式存为 KV 原生元数据; 原生过期以秒计,
+    /// 这里再按毫秒检查一次
+    async fn get_with_metadata(&self, key: &str) -> std::result::Result<Option<Entry>, Error> {
         UnsafeSend(async move {
-            self.table
-                .put_bytes(key, value)
-                .unwrap()
-                .execute()
+            let (value, options) = self
+                .table
+                .get(key)
+                .bytes_with_metadata::<PutOptions>()
                 .await
-                .unwrap();
+                .map_err(storage_err)?;
+            let options = options.unwrap_or_default();
+            if options.is_expired(Date::now().as_millis()) {
+                return Ok(None);
+            }
+            Ok(value.map(|value| Entry::new(value, options)))
         })
         .await
     }
 
-    async fn delete(&self, key: &str) {
+    async fn put_with_options(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &PutOptions,
+    ) -> std::result::Result<(), Error> {
         UnsafeSend(async move {
-            self.table.delete(key).await.unwrap();
+            let mut req = self.table.put_bytes(key, value).map_err(storage_err)?;
+            if *options != PutOptions::default() {
+                req = req.metadata(options).map_err(storage_err)?;
+            }
+            if let Some(at) = options.expires_at {
+                let now = Date::now().as_millis() / 1000;
+                req = req.expiration(at.div_ceil(1000).max(now + MIN_EXPIRATION_SECS));
+            }
+            req.execute().await.map_err(storage_err)
         })
         .await
     }
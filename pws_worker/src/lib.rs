@@ -1,11 +1,17 @@
 mod kv;
-mod sign;
+mod log;
 mod utils;
 
+use std::str::FromStr;
 use std::sync::Arc;
 
+use pws_core::i18n::{Lang, Msg};
+use pws_core::retention::{self, RetentionPolicy};
 use pws_core::routes::router;
-use pws_core::types::{AppState, LogLevel};
+use pws_core::secret::Secret;
+use pws_core::sign::MAX_KEY_LEN;
+use pws_core::store;
+use pws_core::types::{AppState, AppUtils, LogLevel};
 use serde::Deserialize;
 use serde::de::value::Error as DeError;
 use serde::de::value::StrDeserializer;
@@ -14,34 +20,119 @@ use worker::*;
 
 use crate::{kv::WorkerKVStorage, utils::WorkerUtils};
 
-#[event(fetch)]
-async fn fetch(
-    req: HttpRequest,
-    env: Env,
-    _ctx: worker::Context,
-) -> Result<axum::http::Response<axum::body::Body>> {
+fn parse_env<'de, T: Deserialize<'de>>(name: &str, value: &'de str, lang: Lang) -> Result<T> {
+    T::deserialize(StrDeserializer::<DeError>::new(value))
+        .map_err(|_| Error::RustError(Msg::EnvInvalid { name, value }.text(lang)))
+}
+
+/// 未设置时为 `None`, 无法解析时报错
+fn parse_var<T: FromStr>(env: &Env, name: &str, lang: Lang) -> Result<Option<T>> {
+    let Ok(value) = env.var(name) else {
+        return Ok(None);
+    };
+    let value = value.to_string();
+    value.trim().parse().map(Some).map_err(|_| {
+        Error::RustError(
+            Msg::EnvInvalid {
+                name,
+                value: &value,
+            }
+            .text(lang),
+        )
+    })
+}
+
+/// 读取环境变量并初始化日志
+fn load(env: &Env) -> Result<WorkerUtils> {
+    let log_lang = match env.var("LOG_LANG") {
+        Ok(v) => parse_env("LOG_LANG", &v.to_string(), Lang::default())?,
+        Err(_) => Lang::default(),
+    };
+    let env_missing = |name| Error::RustError(Msg::EnvMissing(name).text(log_lang));
+
     let fut = env
         .var("FILE_URL_TEMPLATE")
-        .expect("模板获取失败")
+        .map_err(|_| env_missing("FILE_URL_TEMPLATE"))?
         .to_string();
 
     let sign_key = env
         .secret("SIGN_KEY")
-        .expect("签名KEY获取失败")
+        .map_err(|_| env_missing("SIGN_KEY"))?
         .to_string()
-        .as_bytes()
-        .to_vec();
+        .into_bytes();
+    if sign_key.len() > MAX_KEY_LEN {
+        return Err(Error::RustError(
+            Msg::ConfigFieldInvalid {
+                field: "SIGN_KEY",
+                error: &format!("longer than {} bytes", MAX_KEY_LEN),
+            }
+            .text(log_lang),
+        ));
+    }
 
-    let log_level_str = env.var("LOG_LEVEL").expect("日志等级获取失败").to_string();
-    let deserializer = StrDeserializer::<DeError>::new(&log_level_str);
-    let log_level: LogLevel = LogLevel::deserialize(deserializer).expect("日志等级解析失败");
+    let admin_token = env.secret("ADMIN_TOKEN").ok().map(|s| s.to_string());
 
-    let utils = WorkerUtils {
-        file_url_template: fut,
-        log_level,
-        sign_key,
+    let log_level_str = env
+        .var("LOG_LEVEL")
+        .map_err(|_| env_missing("LOG_LEVEL"))?
+        .to_string();
+    let log_level: LogLevel = parse_env("LOG_LEVEL", &log_level_str, log_lang)?;
+    log::init(log_level);
+
+    let retention = RetentionPolicy {
+        max_age_days: parse_var(env, "RETENTION_MAX_AGE_DAYS", log_lang)?,
+        keep_history: parse_var(env, "RETENTION_KEEP_HISTORY", log_lang)?.unwrap_or(0),
     };
-    let kv = WorkerKVStorage { env: env.clone() };
+
+    Ok(WorkerUtils {
+        file_url_template: fut,
+        log_lang,
+        sign_key: Secret::new(sign_key),
+        admin_token: admin_token.map(Secret::new),
+        retention,
+    })
+}
+
+#[event(fetch)]
+async fn fetch(
+    req: HttpRequest,
+    env: Env,
+    _ctx: worker::Context,
+) -> Result<axum::http::Response<axum::body::Body>> {
+    let utils = load(&env)?;
+    let kv = WorkerKVStorage::new(env.clone())?;
     let state = Arc::new(AppState { utils, kv });
     Ok(router(state).call(req).await?)
 }
+
+/// 写入后这段时间内的 blob 不会被回收, 单位秒
+const DEFAULT_BLOB_GC_GRACE_SECS: u64 = 3600;
+
+/// 由 `wrangler.toml` 中的 cron 触发, 执行数据保留策略并回收不再被引用的 blob
+#[event(scheduled)]
+async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    if let Err(e) = run_scheduled(&env).await {
+        console_error!("{}", e);
+    }
+}
+
+async fn run_scheduled(env: &Env) -> Result<()> {
+    let utils = load(env)?;
+    let lang = utils.log_lang;
+    let dry_run = parse_var(env, "RETENTION_DRY_RUN", lang)?.unwrap_or(false);
+    let grace: u64 =
+        parse_var(env, "BLOB_GC_GRACE_SECS", lang)?.unwrap_or(DEFAULT_BLOB_GC_GRACE_SECS);
+    let kv = WorkerKVStorage::new(env.clone())?;
+    match retention::enforce(&kv, &utils.retention, utils.now(), dry_run).await {
+        Ok(report) => report.log(lang),
+        Err(e) => tracing::error!("{}", e.message(lang)),
+    }
+
+    let before = utils.now().saturating_sub(grace * 1000);
+    match store::collect_blobs(&kv, before).await {
+        Ok(removed) if removed.is_empty() => {}
+        Ok(removed) => tracing::info!("{}", Msg::BlobsCollected(removed.len()).text(lang)),
+        Err(e) => tracing::error!("{}", e.message(lang)),
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::sync::Once;
+
+use pws_core::types::LogLevel;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use worker::{Date, wasm_bindgen::JsValue, web_sys::console};
+
+static INIT: Once = Once::new();
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{:?}", value).into());
+    }
+}
+
+struct SpanFields(Map<String, Value>);
+
+/// 每个事件输出为一行 JSON, 所在 span 的字段平铺到顶层, 便于 Workers Logs 检索
+struct ConsoleJsonLayer;
+
+impl<S> Layer<S> for ConsoleJsonLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut visitor = JsonVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.0));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            let mut visitor = JsonVisitor(std::mem::take(fields));
+            values.record(&mut visitor);
+            *fields = visitor.0;
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut line = Map::new();
+        let mut spans = Vec::new();
+
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                spans.push(Value::from(span.name()));
+                if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                    line.extend(fields.clone());
+                }
+            }
+        }
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        line.extend(visitor.0);
+
+        let level = *event.metadata().level();
+        line.insert("timestamp".to_owned(), Date::now().as_millis().into());
+        line.insert("level".to_owned(), level.as_str().into());
+        line.insert("target".to_owned(), event.metadata().target().into());
+        line.insert("spans".to_owned(), spans.into());
+
+        let text = JsValue::from_str(&Value::Object(line).to_string());
+        match level {
+            Level::ERROR => console::error_1(&text),
+            Level::WARN => console::warn_1(&text),
+            Level::INFO => console::info_1(&text),
+            _ => console::debug_1(&text),
+        }
+    }
+}
+
+/// 同一 isolate 内只初始化一次, 之后的 `LOG_LEVEL` 变更需重新部署
+pub fn init(level: LogLevel) {
+    INIT.call_once(|| {
+        let subscriber =
+            tracing_subscriber::registry().with(ConsoleJsonLayer.with_filter(level.level_filter()));
+        let _ = tracing::subscriber::set_global_default(subscriber);
+    });
+}
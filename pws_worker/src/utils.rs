@@ -4,10 +4,13 @@ use std::{
 };
 
 use async_trait::async_trait;
-use pws_core::types::{AppUtils, LogLevel};
-use worker::{Fetch, Url, wasm_bindgen::JsValue, web_sys::console};
-
-use crate::sign::sign;
+use pws_core::error::Error;
+use pws_core::i18n::Lang;
+use pws_core::retention::RetentionPolicy;
+use pws_core::secret::Secret;
+use pws_core::sign::sign;
+use pws_core::types::AppUtils;
+use worker::{Date, Fetch, Method, Request, RequestInit, Url, js_sys::Math};
 
 pub struct UnsafeSend<F>(pub F);
 
@@ -26,50 +29,72 @@ impl<F: Future> Future for UnsafeSend<F> {
 
 pub struct WorkerUtils {
     pub file_url_template: String,
-    pub sign_key: Vec<u8>,
-    pub log_level: LogLevel,
-}
-
-impl WorkerUtils {
-    fn get_level_str(log_level: LogLevel) -> &'static str {
-        match log_level {
-            LogLevel::DEBUG => "DEBUG",
-            LogLevel::INFO => "INFO",
-            LogLevel::WARN => "WARN",
-            LogLevel::ERROR => "ERROR",
-            LogLevel::UNKNOWN => "UNKNOWN",
-        }
-    }
+    pub sign_key: Secret<Vec<u8>>,
+    pub admin_token: Option<Secret>,
+    pub log_lang: Lang,
+    pub retention: RetentionPolicy,
 }
 
 #[async_trait]
 impl AppUtils for WorkerUtils {
-    async fn get_file(&self, file_obj_id: &str) -> Vec<u8> {
+    async fn get_file(&self, file_obj_id: &str) -> Result<Vec<u8>, Error> {
         let url = self.file_url_template.replace("{file_obj_id}", file_obj_id);
-        let url = Url::parse(&url).expect("url error");
+        let url = Url::parse(&url).map_err(|e| Error::Fetch(e.to_string()))?;
         UnsafeSend(async move {
-            Fetch::Url(url)
+            let mut resp = Fetch::Url(url)
                 .send()
                 .await
-                .expect("Failed to get file")
-                .bytes()
+                .map_err(|e| Error::Fetch(e.to_string()))?;
+            if !(200..300).contains(&resp.status_code()) {
+                return Err(Error::Fetch(format!("HTTP {}", resp.status_code())));
+            }
+            resp.bytes().await.map_err(|e| Error::Fetch(e.to_string()))
+        })
+        .await
+    }
+
+    /// 向存档文件所在主机的根路径发送 HEAD 请求, 任何 HTTP 响应都视为可达
+    async fn ready(&self) -> Result<(), Error> {
+        let mut url =
+            Url::parse(&self.file_url_template).map_err(|e| Error::Fetch(e.to_string()))?;
+        url.set_path("/");
+        url.set_query(None);
+        UnsafeSend(async move {
+            let req =
+                Request::new_with_init(url.as_str(), RequestInit::new().with_method(Method::Head))
+                    .map_err(|e| Error::Fetch(e.to_string()))?;
+            Fetch::Request(req)
+                .send()
                 .await
-                .expect("Failed to read bytes")
+                .map(|_| ())
+                .map_err(|e| Error::Fetch(format!("{}: {}", url, e)))
         })
         .await
     }
 
     fn sign(&self, data: &[u8]) -> String {
-        sign(&self.sign_key, data)
+        sign(self.sign_key.expose(), data)
     }
 
-    fn logger(&self, level: LogLevel, msg: &str) {
-        if self.log_level as u8 <= level as u8 {
-            console::log_1(&JsValue::from_str(&format!(
-                "[{}] {}",
-                Self::get_level_str(level),
-                msg
-            )));
-        }
+    fn request_id(&self) -> String {
+        (0..4)
+            .map(|_| format!("{:08x}", (Math::random() * u32::MAX as f64) as u32))
+            .collect()
+    }
+
+    fn log_lang(&self) -> Lang {
+        self.log_lang
+    }
+
+    fn now(&self) -> u64 {
+        Date::now().as_millis()
+    }
+
+    fn admin_token(&self) -> Option<Secret> {
+        self.admin_token.clone()
+    }
+
+    fn retention(&self) -> RetentionPolicy {
+        self.retention
     }
 }